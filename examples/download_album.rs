@@ -0,0 +1,73 @@
+//! Downloads all the images in an album into a directory.
+//!
+//! ```text
+//! cargo run --example download_album -- <client id> <album id or URL> [directory]
+//! ```
+
+extern crate imgur_api;
+extern crate tokio_core;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::{env, io};
+
+use imgur_api::{AlbumDownloadReport, ImgurClient};
+use tokio_core::reactor::Core;
+
+/// How many images are downloaded at once.
+const MAX_CONCURRENCY: usize = 4;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: download_album <client id> <album id or URL> [directory]");
+        process::exit(2);
+    }
+    let directory = PathBuf::from(args.get(2).map_or(".", |dir| dir.as_str()));
+
+    let mut core = Core::new().expect("couldn't start the event loop");
+    let client = match ImgurClient::new(&core.handle(), args[0].clone()) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("couldn't create the client: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let report = match core.run(client.download_album(args[1].as_str(), MAX_CONCURRENCY)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("couldn't get the album: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = save(&report, &directory) {
+        eprintln!("couldn't save the images to {}: {}", directory.display(), e);
+        process::exit(1);
+    }
+
+    println!(
+        "saved {} images ({} bytes) to {}",
+        report.succeeded_count(),
+        report.total_bytes(),
+        directory.display()
+    );
+    for (id, error) in &report.failed {
+        eprintln!("couldn't download {}: {}", id, error);
+    }
+    if report.failed_count() > 0 {
+        process::exit(1);
+    }
+}
+
+/// Writes the downloaded images to `directory`, numbered in album order.
+fn save(report: &AlbumDownloadReport, directory: &Path) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    for (index, (image, bytes)) in report.succeeded.iter().enumerate() {
+        let extension = image.extension().unwrap_or("jpg");
+        let name = format!("{:03}-{}.{}", index + 1, image.id, extension);
+        fs::write(directory.join(name), bytes)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! A client running on its own reactor thread, for programs without a `tokio_core`
+//! event loop.
+
+use std::fmt;
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+
+use futures::sync::{mpsc, oneshot};
+use futures::{Future, Stream};
+use tokio_core::reactor::Core;
+
+use {Error, ImgurClient, ImgurClientBuilder, Result};
+
+type Job = Box<dyn FnOnce(&ImgurClient) -> Box<dyn Future<Item = (), Error = ()>> + Send>;
+
+/// An `ImgurClient` living on a background thread that runs its own `Core`, e.g. for
+/// GUI apps with an event loop of their own. Created with `ImgurClient::spawn` or
+/// `ImgurClientBuilder::spawn`.
+///
+/// `ImgurClient` is tied to the thread of its `Handle`, so it can't be handed out
+/// directly. Instead, `request` and `run` pass a closure to the background thread,
+/// which starts the request there and sends the result back over a channel.
+///
+/// The thread runs until the `BackgroundClient` is dropped. Dropping it stops new
+/// requests, waits for the ones in flight to finish (see `ImgurClient::drain`) and then
+/// joins the thread, so it can block for as long as the slowest request.
+pub struct BackgroundClient {
+    jobs: Option<mpsc::UnboundedSender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for BackgroundClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BackgroundClient").finish()
+    }
+}
+
+impl BackgroundClient {
+    pub(crate) fn spawn(builder: ImgurClientBuilder) -> Result<BackgroundClient> {
+        let (jobs, receive_jobs) = mpsc::unbounded::<Job>();
+        let (started, wait_started) = std_mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("imgur-client".into())
+            .spawn(move || {
+                let client = Core::new()
+                    .map_err(Error::from)
+                    .and_then(|core| builder.build(&core.handle()).map(|client| (core, client)));
+                let (mut core, client) = match client {
+                    Ok(started_client) => {
+                        let _ = started.send(Ok(()));
+                        started_client
+                    }
+                    Err(e) => {
+                        let _ = started.send(Err(e));
+                        return;
+                    }
+                };
+                let handle = core.handle();
+                let serve = receive_jobs.for_each(|job: Job| {
+                    handle.spawn(job(&client));
+                    Ok(())
+                });
+                let _ = core.run(serve);
+                let _ = core.run(client.drain());
+            })?;
+        match wait_started.recv() {
+            Ok(Ok(())) => Ok(BackgroundClient {
+                jobs: Some(jobs),
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err("the background thread stopped".into()),
+        }
+    }
+
+    /// Starts `request` on the background thread, resolving to its result. The returned
+    /// future can be polled from any thread or event loop, or waited for with `wait`.
+    ///
+    /// ```rust,no_run
+    /// # extern crate imgur_api;
+    /// # extern crate futures;
+    /// # use futures::Future;
+    /// # use imgur_api::{ImgurClient, ResponseFutureExt};
+    /// # fn main() {
+    /// let client = ImgurClient::spawn("client id".into()).unwrap();
+    /// let image = client.request(|client| client.image("PE2NI").into_data());
+    /// println!("{}", image.wait().unwrap().link);
+    /// # }
+    /// ```
+    pub fn request<F, R>(&self, request: F) -> impl Future<Item = R::Item, Error = Error>
+    where
+        F: FnOnce(&ImgurClient) -> R + Send + 'static,
+        R: Future<Error = Error> + 'static,
+        R::Item: Send + 'static,
+    {
+        let (send_result, result) = oneshot::channel();
+        let job: Job = Box::new(move |client: &ImgurClient| {
+            let work = request(client).then(move |result| {
+                let _ = send_result.send(result);
+                Ok(())
+            });
+            let work: Box<dyn Future<Item = (), Error = ()>> = Box::new(work);
+            work
+        });
+        if let Some(ref jobs) = self.jobs {
+            let _ = jobs.unbounded_send(job);
+        }
+        result.then(|result| match result {
+            Ok(result) => result,
+            Err(oneshot::Canceled) => Err("the background thread stopped".into()),
+        })
+    }
+
+    /// Like `request`, but blocks the current thread until the request is done.
+    pub fn run<F, R>(&self, request: F) -> Result<R::Item>
+    where
+        F: FnOnce(&ImgurClient) -> R + Send + 'static,
+        R: Future<Error = Error> + 'static,
+        R::Item: Send + 'static,
+    {
+        self.request(request).wait()
+    }
+}
+
+impl Drop for BackgroundClient {
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+
+    use super::*;
+
+    #[test]
+    fn runs_requests_on_the_background_thread() {
+        let client = ImgurClient::spawn("id".into()).unwrap();
+        let caller = thread::current().id();
+        let (rate_limit, thread) = client
+            .run(|client| future::ok((client.rate_limit(), thread::current().id())))
+            .unwrap();
+        assert_eq!(rate_limit, None);
+        assert_ne!(thread, caller);
+
+        let failed = client.run(|_| future::err::<(), _>("nope".into()));
+        assert_eq!(failed.unwrap_err().to_string(), "nope");
+    }
+}
@@ -0,0 +1,297 @@
+//! An in-memory cache for `image` and `album` responses, enabled with the `cache`
+//! feature.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use {Album, DeleteHash, Image, Response};
+
+/// Successful responses by id, one LRU cache per resource type.
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    images: Lru<Response<Image>>,
+    albums: Lru<Response<Album>>,
+}
+
+impl ResponseCache {
+    /// A cache holding up to `capacity` images and `capacity` albums, each for `ttl`.
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> ResponseCache {
+        ResponseCache {
+            images: Lru::new(capacity, ttl),
+            albums: Lru::new(capacity, ttl),
+        }
+    }
+
+    /// Looks up an unexpired response.
+    pub(crate) fn get<T: Cacheable>(&self, id: &str) -> Option<Response<T>> {
+        T::lru(self).get(id)
+    }
+
+    /// Stores `response` if it's a success; errors are never cached.
+    pub(crate) fn insert<T: Cacheable>(&self, id: &str, response: &Response<T>) {
+        if response.success {
+            T::lru(self).insert(id, response.clone());
+        }
+    }
+
+    /// Drops everything cached about an image after it was changed: the image itself
+    /// and the albums containing it. `id` may also be the image's deletehash, which
+    /// only matches cached copies that came with their deletehash. Returns whether any
+    /// cached image had that id or deletehash.
+    pub(crate) fn forget_image(&self, id: &str) -> bool {
+        let matches = |image: &Image| {
+            image.id == id || image.deletehash.as_ref().map(DeleteHash::as_str) == Some(id)
+        };
+        // The ids the image is cached under, so that a deletehash also finds copies
+        // fetched by id, which don't know their deletehash.
+        let mut ids = vec![id.to_string()];
+        self.images.for_each(|_, resp| {
+            ids.extend(
+                resp.ok()
+                    .filter(|&image| matches(image))
+                    .map(|image| image.id.clone()),
+            )
+        });
+        self.albums.for_each(|_, resp| {
+            let images = resp.ok().and_then(|album| album.images.as_ref());
+            for image in images.into_iter().flatten().filter(|&image| matches(image)) {
+                ids.push(image.id.clone());
+            }
+        });
+        let found = ids.len() > 1;
+
+        let is_it = |image: &Image| ids.contains(&image.id);
+        self.images.retain(|key, resp| {
+            !ids.iter().any(|id| id == key) && !resp.ok().into_iter().any(is_it)
+        });
+        self.albums.retain(|_, resp| {
+            let images = resp.ok().and_then(|album| album.images.as_ref());
+            !images.into_iter().flatten().any(is_it)
+        });
+        found
+    }
+
+    /// Like `forget_image`, after a change made with what may be either the image's id
+    /// or its deletehash. If nothing cached matches, it may be the deletehash of an
+    /// image cached without one, so all images and albums are dropped.
+    pub(crate) fn forget_image_or_deletehash(&self, id_or_hash: &str) {
+        if !self.forget_image(id_or_hash) {
+            self.images.clear();
+            self.albums.clear();
+        }
+    }
+}
+
+/// The types `ResponseCache` can hold.
+pub(crate) trait Cacheable: Clone + Sized {
+    fn lru(cache: &ResponseCache) -> &Lru<Response<Self>>;
+}
+
+impl Cacheable for Image {
+    fn lru(cache: &ResponseCache) -> &Lru<Response<Image>> {
+        &cache.images
+    }
+}
+
+impl Cacheable for Album {
+    fn lru(cache: &ResponseCache) -> &Lru<Response<Album>> {
+        &cache.albums
+    }
+}
+
+/// A least-recently-used cache with expiring entries. Eviction scans all entries,
+/// which is fine for the few thousand entries it's meant for.
+#[derive(Debug)]
+pub(crate) struct Lru<V> {
+    capacity: usize,
+    ttl: Duration,
+    inner: Mutex<LruInner<V>>,
+}
+
+#[derive(Debug)]
+struct LruInner<V> {
+    entries: HashMap<String, Entry<V>>,
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    inserted: Instant,
+    last_used: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize, ttl: Duration) -> Lru<V> {
+        Lru {
+            capacity,
+            ttl,
+            inner: Mutex::new(LruInner {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<V> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let expired = match inner.entries.get_mut(key) {
+            None => return None,
+            Some(entry) if entry.inserted.elapsed() < self.ttl => {
+                entry.last_used = clock;
+                return Some(entry.value.clone());
+            }
+            Some(_) => true,
+        };
+        if expired {
+            inner.entries.remove(key);
+        }
+        None
+    }
+
+    fn insert(&self, key: &str, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        if !inner.entries.contains_key(key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|&(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        let entry = Entry {
+            value,
+            inserted: Instant::now(),
+            last_used: clock,
+        };
+        inner.entries.insert(key.to_string(), entry);
+    }
+
+    fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&str, &V),
+    {
+        let inner = self.inner.lock().unwrap();
+        for (key, entry) in &inner.entries {
+            f(key, &entry.value);
+        }
+    }
+
+    fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    /// Keeps only the entries for which `keep` returns `true`.
+    fn retain<F>(&self, mut keep: F)
+    where
+        F: FnMut(&str, &V) -> bool,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.retain(|key, entry| keep(key, &entry.value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tests::{album_fixture, image_fixture};
+    use {ApiError, ResponseData};
+
+    fn ok<T>(data: T) -> Response<T> {
+        Response {
+            status: 200,
+            success: true,
+            data: ResponseData::Success(data),
+            raw: None,
+            rate_limit: None,
+            server_timing: None,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let lru = Lru::new(2, Duration::from_secs(60));
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get("a"), Some(1));
+        lru.insert("c", 3);
+        assert_eq!(lru.get("b"), None);
+        assert_eq!(lru.get("a"), Some(1));
+        assert_eq!(lru.get("c"), Some(3));
+    }
+
+    #[test]
+    fn entries_expire() {
+        let lru = Lru::new(2, Duration::from_secs(0));
+        lru.insert("a", 1);
+        assert_eq!(lru.get("a"), None);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        let error: Response<Image> = Response {
+            status: 404,
+            success: false,
+            data: ResponseData::Error(ApiError {
+                error: "Not found".into(),
+                request: "/3/image/abc".into(),
+                method: "GET".into(),
+            }),
+            raw: None,
+            rate_limit: None,
+            server_timing: None,
+        };
+        cache.insert("abc", &error);
+        assert!(cache.get::<Image>("abc").is_none());
+    }
+
+    #[test]
+    fn forgets_changed_images() {
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        let mut image = image_fixture("abc", 100);
+        image.deletehash = Some("hash".to_string().into());
+        let mut album = album_fixture("def", 0);
+        album.images = Some(vec![image.clone()]);
+        cache.insert("abc", &ok(image));
+        cache.insert("def", &ok(album));
+        cache.insert("other", &ok(image_fixture("other", 100)));
+
+        assert!(cache.forget_image("hash"));
+        assert!(cache.get::<Image>("abc").is_none());
+        assert!(cache.get::<Album>("def").is_none());
+        assert!(cache.get::<Image>("other").is_some());
+    }
+
+    #[test]
+    fn deletehashes_find_images_cached_by_id() {
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        let mut image = image_fixture("abc", 100);
+        cache.insert("abc", &ok(image.clone()));
+        image.deletehash = Some("hash".to_string().into());
+        let mut album = album_fixture("def", 0);
+        album.images = Some(vec![image]);
+        cache.insert("def", &ok(album));
+        cache.insert("other", &ok(image_fixture("other", 100)));
+
+        cache.forget_image_or_deletehash("hash");
+        assert!(cache.get::<Image>("abc").is_none());
+        assert!(cache.get::<Album>("def").is_none());
+        assert!(cache.get::<Image>("other").is_some());
+
+        cache.insert("abc", &ok(image_fixture("abc", 100)));
+        cache.forget_image_or_deletehash("unknown");
+        assert!(cache.get::<Image>("abc").is_none());
+        assert!(cache.get::<Image>("other").is_none());
+    }
+}
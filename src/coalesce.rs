@@ -0,0 +1,246 @@
+//! Sharing one request between concurrent identical `GET`s, enabled with
+//! `ImgurClientBuilder::coalesce_requests`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use futures::future::{Future, Shared};
+use futures::{Async, Poll};
+
+use {Error, ErrorKind, RawBody};
+
+type Pending = Shared<Box<dyn Future<Item = RawBody, Error = Error>>>;
+
+/// The `GET` requests in flight, by URL. Clones share the same requests.
+#[derive(Clone, Default)]
+pub(crate) struct InFlightGets {
+    pending: Arc<Mutex<Gets>>,
+}
+
+#[derive(Default)]
+struct Gets {
+    by_url: HashMap<String, Request>,
+    next_id: u64,
+}
+
+struct Request {
+    /// Tells the request apart from later ones to the same URL.
+    id: u64,
+    shared: Pending,
+    /// The `Joined` futures still alive for this request.
+    waiters: usize,
+}
+
+impl fmt::Debug for InFlightGets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InFlightGets")
+            .field("pending", &self.len())
+            .finish()
+    }
+}
+
+impl InFlightGets {
+    /// Waits for the request to `url` that is already in flight, or starts one with
+    /// `start`. The request is forgotten once it's done, so later calls start a new
+    /// one. It's also forgotten, and dropped, once every `Joined` waiting for it is
+    /// dropped, so that an abandoned request doesn't keep `ImgurClient::drain` waiting.
+    pub(crate) fn join<F, S>(&self, url: String, start: S) -> Joined
+    where
+        F: Future<Item = RawBody, Error = Error> + 'static,
+        S: FnOnce() -> F,
+    {
+        let mut pending = self.pending.lock().unwrap();
+        let pending = &mut *pending;
+        let (id, shared) = match pending.by_url.get_mut(&url) {
+            Some(request) => {
+                request.waiters += 1;
+                (request.id, request.shared.clone())
+            }
+            None => {
+                let id = pending.next_id;
+                pending.next_id += 1;
+                let gets = self.clone();
+                let key = url.clone();
+                let work = start().then(move |result| {
+                    gets.forget(&key, id);
+                    result
+                });
+                let work: Box<dyn Future<Item = RawBody, Error = Error>> = Box::new(work);
+                let shared = work.shared();
+                let request = Request {
+                    id,
+                    shared: shared.clone(),
+                    waiters: 1,
+                };
+                pending.by_url.insert(url.clone(), request);
+                (id, shared)
+            }
+        };
+        Joined {
+            shared,
+            gets: self.clone(),
+            url,
+            id,
+        }
+    }
+
+    /// The number of requests in flight.
+    pub(crate) fn len(&self) -> usize {
+        self.pending.lock().unwrap().by_url.len()
+    }
+
+    /// Removes the request `id` to `url`, if it's still there.
+    fn forget(&self, url: &str, id: u64) {
+        let removed = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.by_url.get(url) {
+                Some(request) if request.id == id => pending.by_url.remove(url),
+                _ => None,
+            }
+        };
+        // Dropped outside the lock, as this can drop the request itself.
+        drop(removed);
+    }
+
+    /// Called when a `Joined` for the request `id` to `url` is dropped.
+    fn leave(&self, url: &str, id: u64) {
+        let removed = {
+            let mut pending = self.pending.lock().unwrap();
+            let abandoned = match pending.by_url.get_mut(url) {
+                Some(request) if request.id == id => {
+                    request.waiters -= 1;
+                    request.waiters == 0
+                }
+                _ => false,
+            };
+            if abandoned {
+                pending.by_url.remove(url)
+            } else {
+                None
+            }
+        };
+        drop(removed);
+    }
+}
+
+/// The future returned by `InFlightGets::join`, resolving to a copy of the shared
+/// response.
+pub(crate) struct Joined {
+    shared: Pending,
+    gets: InFlightGets,
+    url: String,
+    id: u64,
+}
+
+impl fmt::Debug for Joined {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Joined").field("url", &self.url).finish()
+    }
+}
+
+impl Future for Joined {
+    type Item = RawBody;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<RawBody, Error> {
+        match self.shared.poll() {
+            Ok(Async::Ready(body)) => Ok(Async::Ready((*body).clone())),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(error) => Err(copy_error(&error)),
+        }
+    }
+}
+
+impl Drop for Joined {
+    fn drop(&mut self) {
+        self.gets.leave(&self.url, self.id);
+    }
+}
+
+/// A copy of an error shared between several requests. The kinds that can come up
+/// before a body is parsed are kept, anything else only keeps its message.
+fn copy_error(error: &Error) -> Error {
+    let kind = match *error.kind() {
+        ErrorKind::Network => ErrorKind::Network,
+        ErrorKind::TlsHandshake => ErrorKind::TlsHandshake,
+        ErrorKind::Timeout => ErrorKind::Timeout,
+        ErrorKind::Draining => ErrorKind::Draining,
+        ErrorKind::BodyTooLarge(max_body_bytes) => ErrorKind::BodyTooLarge(max_body_bytes),
+        ErrorKind::ServiceUnavailable(status, ref snippet) => {
+            ErrorKind::ServiceUnavailable(status, snippet.clone())
+        }
+        _ => ErrorKind::Msg(error.to_string()),
+    };
+    kind.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::future;
+    use futures::sync::oneshot;
+
+    use super::*;
+
+    fn body(bytes: &[u8]) -> RawBody {
+        RawBody {
+            body: bytes.to_vec(),
+            rate_limit: None,
+            server_timing: None,
+        }
+    }
+
+    #[test]
+    fn shares_requests_in_flight() {
+        let gets = InFlightGets::default();
+        let started = Rc::new(Cell::new(0));
+        let (send, receive) = oneshot::channel();
+        let mut receive = Some(receive);
+        let mut start = || {
+            started.set(started.get() + 1);
+            receive.take().unwrap().map_err(|_| Error::from("canceled"))
+        };
+        let first = gets.join("a".into(), &mut start);
+        let second = gets.join("a".into(), &mut start);
+        assert_eq!(started.get(), 1);
+
+        send.send(body(b"{}")).unwrap();
+        let (first, second) = first.join(second).wait().unwrap();
+        assert_eq!((first.body, second.body), (b"{}".to_vec(), b"{}".to_vec()));
+
+        let third = gets.join("a".into(), || future::ok(body(b"[]")));
+        assert_eq!(third.wait().unwrap().body, b"[]");
+        assert_eq!(gets.len(), 0);
+    }
+
+    #[test]
+    fn forgets_abandoned_requests() {
+        let gets = InFlightGets::default();
+        let (send, receive) = oneshot::channel::<RawBody>();
+        let first = gets.join("a".into(), || receive.map_err(|_| Error::from("canceled")));
+        let second = gets.join("a".into(), || future::ok(body(b"[]")));
+        drop(first);
+        assert_eq!(gets.len(), 1);
+        drop(second);
+        assert_eq!(gets.len(), 0);
+        assert!(send.is_canceled());
+
+        let third = gets.join("a".into(), || future::ok(body(b"[]")));
+        assert_eq!(third.wait().unwrap().body, b"[]");
+    }
+
+    #[test]
+    fn copies_errors() {
+        let gets = InFlightGets::default();
+        let failed = gets.join("a".into(), || future::err(ErrorKind::Timeout.into()));
+        match *failed.wait().unwrap_err().kind() {
+            ErrorKind::Timeout => {}
+            ref other => panic!("expected a timeout, got {:?}", other),
+        }
+        let error = copy_error(&Error::from("something broke"));
+        assert_eq!(error.to_string(), "something broke");
+    }
+}
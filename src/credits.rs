@@ -0,0 +1,156 @@
+//! Keeping track of imgur's rate limits, which are counted in credits.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::header::Headers;
+
+/// The rate limit state imgur reports in the headers of every API response.
+///
+/// imgur limits both the application (the client id) and the user (by IP address, or
+/// by account with an access token); a request fails with `429 Too Many Requests` when
+/// either runs out of credits. Any value imgur didn't send is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimitInfo {
+    /// Credits available to the user per hour.
+    pub user_limit: Option<u64>,
+    /// Credits the user has left.
+    pub user_remaining: Option<u64>,
+    /// When the user's credits are reset, as a unix timestamp.
+    pub user_reset: Option<u64>,
+    /// Credits available to the application per day.
+    pub client_limit: Option<u64>,
+    /// Credits the application has left.
+    pub client_remaining: Option<u64>,
+}
+
+impl RateLimitInfo {
+    /// Reads the `X-RateLimit-*` headers, returning `None` if there are none.
+    pub(crate) fn from_headers(headers: &Headers) -> Option<RateLimitInfo> {
+        let header = |name: &str| {
+            headers
+                .get_raw(name)
+                .and_then(|raw| raw.one())
+                .and_then(|value| ::std::str::from_utf8(value).ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+        let info = RateLimitInfo {
+            user_limit: header("X-RateLimit-UserLimit"),
+            user_remaining: header("X-RateLimit-UserRemaining"),
+            user_reset: header("X-RateLimit-UserReset"),
+            client_limit: header("X-RateLimit-ClientLimit"),
+            client_remaining: header("X-RateLimit-ClientRemaining"),
+        };
+        if info == RateLimitInfo::default() {
+            None
+        } else {
+            Some(info)
+        }
+    }
+
+    /// The credits left before hitting either limit, if imgur reported any.
+    pub fn remaining(&self) -> Option<u64> {
+        match (self.user_remaining, self.client_remaining) {
+            (Some(user), Some(client)) => Some(user.min(client)),
+            (user, client) => user.or(client),
+        }
+    }
+}
+
+/// The credits left, as returned by `ImgurClient::credits` (`GET /credits`).
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Credits {
+    pub user_limit: u64,
+    pub user_remaining: u64,
+    /// When the user's credits are reset, as a unix timestamp. See `reset_at`.
+    pub user_reset: u64,
+    pub client_limit: u64,
+    pub client_remaining: u64,
+}
+
+impl Credits {
+    /// When the user's credits are reset to `user_limit`.
+    ///
+    /// This is only about the user's (hourly) credits. The application's credits are
+    /// counted per day and imgur doesn't say when they are reset; according to its
+    /// documentation, running out of them five times in a month blocks the application
+    /// for the rest of the month.
+    pub fn reset_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.user_reset)
+    }
+}
+
+/// The kinds of requests the client makes, for `estimated_cost`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Fetching data, e.g. `image`, `album` or `gallery`.
+    Get,
+    /// Uploading an image with `upload_image`.
+    Upload,
+    /// Changing data other than uploads, e.g. `delete_image` or `add_gallery_tags`.
+    Post,
+    /// Downloading files from imgur's CDN with `download`.
+    Download,
+}
+
+/// The number of credits a request to `endpoint` costs, according to imgur's
+/// documentation: uploads cost 10 credits, other API requests 1, and downloads from the
+/// CDN don't count against the rate limit.
+pub fn estimated_cost(endpoint: Endpoint) -> u32 {
+    match endpoint {
+        Endpoint::Get | Endpoint::Post => 1,
+        Endpoint::Upload => 10,
+        Endpoint::Download => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        let mut headers = Headers::new();
+        assert_eq!(RateLimitInfo::from_headers(&headers), None);
+
+        headers.set_raw("X-RateLimit-UserLimit", "2000");
+        headers.set_raw("X-RateLimit-UserRemaining", "1990");
+        headers.set_raw("X-RateLimit-UserReset", "1519744931");
+        headers.set_raw("X-RateLimit-ClientLimit", "12500");
+        headers.set_raw("X-RateLimit-ClientRemaining", "junk");
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.user_limit, Some(2000));
+        assert_eq!(info.user_reset, Some(1_519_744_931));
+        assert_eq!(info.client_remaining, None);
+        assert_eq!(info.remaining(), Some(1990));
+    }
+
+    #[test]
+    fn remaining_is_the_lower_limit() {
+        let info = RateLimitInfo {
+            user_remaining: Some(50),
+            client_remaining: Some(20),
+            ..RateLimitInfo::default()
+        };
+        assert_eq!(info.remaining(), Some(20));
+        assert_eq!(RateLimitInfo::default().remaining(), None);
+    }
+
+    #[test]
+    fn deserialize_credits() {
+        let json = r#"{"UserLimit": 500, "UserRemaining": 499, "UserReset": 1519744931,
+            "ClientLimit": 12500, "ClientRemaining": 12447}"#;
+        let credits: Credits = ::serde_json::from_str(json).unwrap();
+        assert_eq!(credits.client_remaining, 12447);
+        let reset = credits.reset_at().duration_since(UNIX_EPOCH).unwrap();
+        assert_eq!(reset.as_secs(), 1_519_744_931);
+    }
+
+    #[test]
+    fn costs() {
+        assert_eq!(estimated_cost(Endpoint::Get), 1);
+        assert_eq!(estimated_cost(Endpoint::Upload), 10);
+        assert_eq!(estimated_cost(Endpoint::Download), 0);
+    }
+}
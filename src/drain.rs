@@ -0,0 +1,152 @@
+//! Refusing new requests and waiting for the ones in flight, for graceful shutdown.
+
+use std::sync::{Arc, Mutex};
+
+use futures::task::{self, Task};
+use futures::{Async, Future, Poll, Stream};
+
+use {Error, ErrorKind, Result};
+
+/// Counts the requests in flight. Shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct Requests {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    draining: bool,
+    waiting: Vec<Task>,
+}
+
+impl Requests {
+    /// Registers a new request, or fails with `ErrorKind::Draining` once `drain` was
+    /// called. The request counts as in flight until the guard is dropped.
+    pub(crate) fn start(requests: &Arc<Requests>) -> Result<InFlight> {
+        let mut state = requests.state.lock().unwrap();
+        if state.draining {
+            return Err(ErrorKind::Draining.into());
+        }
+        state.in_flight += 1;
+        Ok(InFlight(requests.clone()))
+    }
+
+    /// Refuses new requests from now on.
+    pub(crate) fn drain(requests: &Arc<Requests>) -> Drain {
+        requests.state.lock().unwrap().draining = true;
+        Drain(requests.clone())
+    }
+}
+
+/// Marks a request as in flight while it's alive.
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<Requests>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.in_flight -= 1;
+        if state.in_flight == 0 {
+            for task in state.waiting.drain(..) {
+                task.notify();
+            }
+        }
+    }
+}
+
+/// A future or stream that counts as an in-flight request until it's finished.
+#[derive(Debug)]
+pub(crate) struct Tracked<F> {
+    inner: F,
+    guard: Option<InFlight>,
+}
+
+impl<F> Tracked<F> {
+    pub(crate) fn new(inner: F, guard: InFlight) -> Tracked<F> {
+        Tracked {
+            inner,
+            guard: Some(guard),
+        }
+    }
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let poll = self.inner.poll();
+        if let Ok(Async::NotReady) = poll {
+            return poll;
+        }
+        self.guard.take();
+        poll
+    }
+}
+
+impl<S: Stream> Stream for Tracked<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let poll = self.inner.poll();
+        match poll {
+            Ok(Async::Ready(None)) | Err(_) => {
+                self.guard.take();
+            }
+            _ => {}
+        }
+        poll
+    }
+}
+
+/// The future returned by `ImgurClient::drain`, which resolves once no requests are in
+/// flight anymore.
+#[derive(Debug)]
+pub struct Drain(Arc<Requests>);
+
+impl Future for Drain {
+    type Item = ();
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<(), Error> {
+        let mut state = self.0.state.lock().unwrap();
+        if state.in_flight == 0 {
+            Ok(Async::Ready(()))
+        } else {
+            // A drain polled again before it's done is already waiting.
+            if !state.waiting.iter().any(Task::will_notify_current) {
+                state.waiting.push(task::current());
+            }
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+
+    use super::*;
+
+    #[test]
+    fn waits_once_per_task() {
+        let requests = Arc::new(Requests::default());
+        let guard = Requests::start(&requests).unwrap();
+        let mut drain = Requests::drain(&requests);
+        future::lazy(|| {
+            for _ in 0..3 {
+                assert!(drain.poll().unwrap().is_not_ready());
+            }
+            Ok::<(), ()>(())
+        })
+        .wait()
+        .unwrap();
+        assert_eq!(requests.state.lock().unwrap().waiting.len(), 1);
+
+        drop(guard);
+        assert!(requests.state.lock().unwrap().waiting.is_empty());
+        drain.wait().unwrap();
+    }
+}
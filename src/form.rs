@@ -0,0 +1,44 @@
+//! `application/x-www-form-urlencoded` encoding.
+
+use std::fmt::Write;
+
+/// Percent-encodes everything except the unreserved characters of RFC 3986.
+pub(crate) fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+    encoded
+}
+
+/// Encodes `pairs` as a form body (or query string).
+pub(crate) fn urlencoded(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|&(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_reserved_characters() {
+        assert_eq!(percent_encode("a b&c=d/é~"), "a%20b%26c%3Dd%2F%C3%A9~");
+    }
+
+    #[test]
+    fn encodes_pairs() {
+        assert_eq!(
+            urlencoded(&[("tags", "cats,funny"), ("x", "")]),
+            "tags=cats%2Cfunny&x="
+        );
+    }
+}
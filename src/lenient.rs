@@ -0,0 +1,159 @@
+//! Deserializers for fields that imgur encodes inconsistently across endpoints.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+
+struct BoolOrInt;
+
+impl<'de> Visitor<'de> for BoolOrInt {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a boolean, 0 or 1")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<bool, E> {
+        Ok(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<bool, E> {
+        match value {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(de::Unexpected::Unsigned(value), &self)),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<bool, E> {
+        match value {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+        }
+    }
+}
+
+struct NumberOrString;
+
+impl<'de> Visitor<'de> for NumberOrString {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-negative number, possibly as a string")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<usize, E> {
+        Ok(value as usize)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<usize, E> {
+        if value < 0 {
+            return Err(E::invalid_value(de::Unexpected::Signed(value), &self));
+        }
+        Ok(value as usize)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<usize, E> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+struct OptionBoolOrInt;
+
+impl<'de> Visitor<'de> for OptionBoolOrInt {
+    type Value = Option<bool>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a boolean, 0, 1 or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Option<bool>, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Option<bool>, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<bool>, D::Error> {
+        bool_or_int(deserializer).map(Some)
+    }
+}
+
+/// Accepts `true`/`false` as well as `1`/`0`, which some endpoints send instead.
+pub(crate) fn bool_or_int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    deserializer.deserialize_any(BoolOrInt)
+}
+
+/// Like `bool_or_int`, for optional fields. Use together with `#[serde(default)]`, so
+/// that a missing field is still `None`.
+pub(crate) fn option_bool_or_int<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    deserializer.deserialize_option(OptionBoolOrInt)
+}
+
+/// Accepts a number as well as a numeric string such as `"429"`, which some error
+/// responses send for `status`.
+pub(crate) fn number_or_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    deserializer.deserialize_any(NumberOrString)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    #[derive(Debug, Deserialize)]
+    struct Flags {
+        #[serde(deserialize_with = "super::bool_or_int")]
+        required: bool,
+        #[serde(default, deserialize_with = "super::option_bool_or_int")]
+        optional: Option<bool>,
+    }
+
+    fn parse(json: &str) -> Result<Flags, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn accepts_booleans_and_ints() {
+        assert!(parse(r#"{"required": true}"#).unwrap().required);
+        assert!(parse(r#"{"required": 1}"#).unwrap().required);
+        assert!(!parse(r#"{"required": 0}"#).unwrap().required);
+        assert!(!parse(r#"{"required": false}"#).unwrap().required);
+        assert!(parse(r#"{"required": 2}"#).is_err());
+        assert!(parse(r#"{"required": "yes"}"#).is_err());
+    }
+
+    #[test]
+    fn accepts_missing_and_null_options() {
+        assert_eq!(parse(r#"{"required": 1}"#).unwrap().optional, None);
+        let flags = parse(r#"{"required": 1, "optional": null}"#).unwrap();
+        assert_eq!(flags.optional, None);
+        let flags = parse(r#"{"required": 1, "optional": 1}"#).unwrap();
+        assert_eq!(flags.optional, Some(true));
+        let flags = parse(r#"{"required": 1, "optional": false}"#).unwrap();
+        assert_eq!(flags.optional, Some(false));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Status {
+        #[serde(deserialize_with = "super::number_or_string")]
+        status: usize,
+    }
+
+    #[test]
+    fn accepts_numbers_and_numeric_strings() {
+        let parse = |json| serde_json::from_str::<Status>(json).map(|s| s.status);
+        assert_eq!(parse(r#"{"status": 429}"#).unwrap(), 429);
+        assert_eq!(parse(r#"{"status": "429"}"#).unwrap(), 429);
+        assert!(parse(r#"{"status": "Too Many Requests"}"#).is_err());
+        assert!(parse(r#"{"status": -1}"#).is_err());
+    }
+}
@@ -19,6 +19,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+#[cfg(feature = "tokio-core")]
 extern crate tokio_core;
 
 mod errors {
@@ -29,253 +30,5467 @@ mod errors {
 
     error_chain! {
         foreign_links {
-            Tls(native_tls::Error);
-            Hyper(hyper::Error);
-            Serde(serde_json::Error);
-            Imgur(super::ApiError);
+            Tls(native_tls::Error) #[doc = "TLS setup failed."];
+            Hyper(hyper::Error) #[doc = "The HTTP request failed."];
+            Serde(serde_json::Error) #[doc = "The response could not be deserialized."];
+            Imgur(super::ApiError) #[doc = "The API returned an error."];
+            Io(::std::io::Error) #[doc = "An I/O error occurred."];
+        }
+
+        errors {
+            /// The input could not be interpreted as an imgur id or URL.
+            InvalidId(input: String) {
+                description("invalid imgur id or URL")
+                display("invalid imgur id or URL: '{}'", input)
+            }
+            /// Couldn't reach imgur: the connection was refused, reset or the host didn't
+            /// resolve. Usually worth retrying.
+            Network {
+                description("network error")
+                display("could not reach the server")
+            }
+            /// The TLS handshake failed. Usually a configuration problem, not worth
+            /// retrying.
+            TlsHandshake {
+                description("TLS handshake failed")
+                display("TLS handshake failed")
+            }
+            /// The request timed out.
+            Timeout {
+                description("request timed out")
+                display("request timed out")
+            }
+            /// Downloading a file failed with a non-success HTTP status.
+            DownloadFailed(url: String, status: u16) {
+                description("download failed")
+                display("downloading {} failed with status {}", url, status)
+            }
+            /// A download was redirected more than the client's `max_redirects` times.
+            TooManyRedirects(url: String, max_redirects: u32) {
+                description("too many redirects")
+                display("downloading {} took more than {} redirects", url, max_redirects)
+            }
+            /// A download was redirected back to a URL it had already visited.
+            RedirectLoop(url: String) {
+                description("redirect loop")
+                display("redirect loop at {}", url)
+            }
+            /// A string didn't match any of the values of an option such as `Sort`.
+            InvalidValue(what: String, input: String, valid: String) {
+                description("invalid value")
+                display("invalid {} '{}', expected one of: {}", what, input, valid)
+            }
+            /// A field was rejected before sending the request, e.g. an upload title
+            /// longer than `MAX_TITLE_LENGTH`.
+            InvalidInput(field: String, max_length: usize) {
+                description("invalid input")
+                display("{} is longer than {} characters", field, max_length)
+            }
+            /// A request that needs a user's access token was made with just a client id
+            /// (see `ImgurClientBuilder::auth`).
+            Unauthorized {
+                description("access token required")
+                display("this request requires an access token (Auth::Bearer)")
+            }
+            /// An API response body was larger than the client's `max_body_bytes`.
+            BodyTooLarge(max_body_bytes: usize) {
+                description("response body too large")
+                display("the response body is larger than {} bytes", max_body_bytes)
+            }
+            /// imgur answered with an HTML page instead of JSON, usually its maintenance
+            /// or outage page. Contains the status and the start of the page's text.
+            ServiceUnavailable(status: u16, snippet: String) {
+                description("imgur is unavailable")
+                display("imgur returned an HTML page (status {}), it may be down: {}", status, snippet)
+            }
+            /// A batch job was refused because it would cost more credits than are left
+            /// (see `ImgurClientBuilder::refuse_over_budget`).
+            InsufficientCredits(needed: u64, remaining: u64) {
+                description("not enough credits left")
+                display("the job needs about {} credits but only {} are left", needed, remaining)
+            }
+            /// The client is being drained (see `ImgurClient::drain`) and doesn't start new
+            /// requests.
+            Draining {
+                description("client is draining")
+                display("the client is shutting down and doesn't accept new requests")
+            }
+            /// The input refers to a different kind of resource than was requested.
+            WrongResourceKind(expected: super::ResourceKind, found: super::ResourceKind) {
+                description("wrong kind of imgur resource")
+                display("expected {:?} id, found {:?} id", expected, found)
+            }
         }
     }
 }
 
-use std::{error, fmt};
+#[cfg(feature = "tokio-core")]
+mod background;
+#[cfg(feature = "cache")]
+mod cache;
+mod coalesce;
+mod credits;
+mod drain;
+mod form;
+mod lenient;
+pub mod messages;
+mod multipart;
+pub mod notifications;
+pub mod oauth;
+mod pagination;
+mod retry;
+mod streaming;
+mod throttle;
+mod timer;
 
-use hyper::{Client, Method, Request, Uri};
+use std::{cmp, error, fmt, io};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::{Chunk, Client, Method, Request, StatusCode, Uri};
 use hyper::client::HttpConnector;
-use hyper::header::Authorization;
+use hyper::header::{Authorization, ContentLength, ContentType, Headers, Location};
 use hyper_tls::HttpsConnector;
+#[cfg(feature = "tokio-core")]
 use tokio_core::reactor::Handle;
-use futures::{future, Future, Stream};
-use serde::de::DeserializeOwned;
+use futures::{future, stream, Future, Stream};
+use futures::future::{AndThen, Either, Loop};
+use multipart::Multipart;
+use serde::{Deserialize, Deserializer};
+use serde::de::{self, DeserializeOwned};
 
-pub use errors::{Error, Result};
+#[cfg(feature = "tokio-core")]
+pub use background::BackgroundClient;
+pub use credits::{estimated_cost, Credits, Endpoint, RateLimitInfo};
+pub use drain::Drain;
+pub use pagination::{Page, Paginator};
+pub use errors::{Error, ErrorKind, Result};
+pub use retry::RetryPolicy;
+pub use throttle::Throttle;
+pub use timer::Timer;
 
+#[cfg(feature = "tokio-core")]
 const DEFAULT_THREADS: usize = 2;
 const API: &str = "https://api.imgur.com/3";
+const DEFAULT_MAX_REDIRECTS: u32 = 5;
+const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+/// The number of images on a full page of `/account/<username>/images`.
+const ACCOUNT_IMAGES_PAGE_SIZE: usize = 50;
+/// The number of posts on a full page of the gallery feeds.
+const GALLERY_PAGE_SIZE: usize = 60;
+
+/// The longest image title imgur accepts, in characters. Longer titles are rejected
+/// by `upload_image` without making a request.
+pub const MAX_TITLE_LENGTH: usize = 255;
+/// The longest image description imgur accepts, in characters. Longer descriptions
+/// are rejected by `upload_image` without making a request.
+pub const MAX_DESCRIPTION_LENGTH: usize = 5000;
 
 type HttpsClient = Client<HttpsConnector<HttpConnector>>;
 
 /// Main client type.
-#[derive(Debug, Clone)]
+///
+/// Like `Auth`, the `Debug` output leaves out credentials, including the values of
+/// headers added with `ImgurClientBuilder::header`.
+#[derive(Clone)]
 pub struct ImgurClient {
     client: HttpsClient,
-    client_id: String,
+    timer: Rc<dyn Timer>,
+    auth: Auth,
+    retry_policy: RetryPolicy,
+    capture_raw: bool,
+    max_redirects: u32,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<cache::ResponseCache>>,
+    requests: Arc<drain::Requests>,
+    rate_limit: Arc<Mutex<Option<RateLimitInfo>>>,
+    refuse_over_budget: bool,
+    accept_language: Option<String>,
+    max_body_bytes: usize,
+    throttle: Option<Throttle>,
+    extra_headers: Vec<(String, String)>,
+    in_flight_gets: Option<coalesce::InFlightGets>,
+    max_pages: Option<u32>,
+}
+
+impl fmt::Debug for ImgurClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("ImgurClient");
+        debug
+            .field("client", &self.client)
+            .field("timer", &self.timer)
+            .field("auth", &self.auth)
+            .field("retry_policy", &self.retry_policy)
+            .field("capture_raw", &self.capture_raw)
+            .field("max_redirects", &self.max_redirects);
+        #[cfg(feature = "cache")]
+        debug.field("cache", &self.cache);
+        debug
+            .field("requests", &self.requests)
+            .field("rate_limit", &self.rate_limit)
+            .field("refuse_over_budget", &self.refuse_over_budget)
+            .field("accept_language", &self.accept_language)
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("throttle", &self.throttle)
+            .field("extra_headers", &RedactedHeaders(&self.extra_headers))
+            .field("in_flight_gets", &self.in_flight_gets)
+            .field("max_pages", &self.max_pages)
+            .finish()
+    }
+}
+
+/// Formats headers as their names, with every value shown as `<redacted>`.
+struct RedactedHeaders<'a>(&'a [(String, String)]);
+
+impl<'a> fmt::Debug for RedactedHeaders<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, _)| (name, Redacted)))
+            .finish()
+    }
+}
+
+struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
 }
 
 impl ImgurClient {
     /// Create a new `ImgurClient`.
+    #[cfg(feature = "tokio-core")]
     pub fn new(handle: &Handle, client_id: String) -> Result<ImgurClient> {
-        let connector = HttpsConnector::new(DEFAULT_THREADS, handle)?;
-        let client = Client::configure().connector(connector).build(handle);
-        Ok(ImgurClient { client, client_id })
+        ImgurClient::builder(client_id).build(handle)
+    }
+
+    /// Create a new `ImgurClient` authenticated as a user, with an OAuth2 access token
+    /// obtained elsewhere.
+    #[cfg(feature = "tokio-core")]
+    pub fn with_access_token(handle: &Handle, access_token: String) -> Result<ImgurClient> {
+        ImgurClient::builder(String::new())
+            .auth(Auth::Bearer(access_token))
+            .build(handle)
     }
 
     ///  Create a new `ImgurClient` with a supplied `hyper::Client`.
+    ///
+    /// The client must have been built with a `Handle`, not a custom executor; use
+    /// `ImgurClientBuilder::build_with_timer` for the latter.
+    #[cfg(feature = "tokio-core")]
     pub fn with_client(client: HttpsClient, client_id: String) -> ImgurClient {
-        ImgurClient { client, client_id }
+        #[allow(deprecated)]
+        let handle = client.handle().clone();
+        ImgurClient::builder(client_id).build_with_client(client, &handle)
+    }
+
+    /// Create a client running on a background thread with its own event loop, for
+    /// programs that don't run a `tokio_core::reactor::Core` themselves. See
+    /// `BackgroundClient`; use `ImgurClientBuilder::spawn` to configure it.
+    #[cfg(feature = "tokio-core")]
+    pub fn spawn(client_id: String) -> Result<BackgroundClient> {
+        ImgurClient::builder(client_id).spawn()
+    }
+
+    /// Create an `ImgurClientBuilder` for configuring a client beyond its client id.
+    pub fn builder(client_id: String) -> ImgurClientBuilder {
+        ImgurClientBuilder {
+            auth: Auth::ClientId(client_id),
+            retry_policy: RetryPolicy::default(),
+            capture_raw: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            refuse_over_budget: false,
+            accept_language: None,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            throttle: None,
+            extra_headers: Vec::new(),
+            coalesce_requests: false,
+            max_pages: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+            insecure_tls: InsecureTls::default(),
+        }
+    }
+
+    /// The rate limits reported with the latest API response, shared by all clones of
+    /// the client. `None` until the first response.
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Fails if `refuse_over_budget` is set and a job costing `cost` credits would
+    /// exceed the credits left.
+    fn check_budget(&self, cost: u64) -> Result<()> {
+        let remaining = self.rate_limit().and_then(|info| info.remaining());
+        match remaining {
+            Some(remaining) if self.refuse_over_budget && cost > remaining => {
+                Err(ErrorKind::InsufficientCredits(cost, remaining).into())
+            }
+            _ => Ok(()),
+        }
     }
 
-    fn get_with_header<T>(&self, url: Uri) -> impl Future<Item = T, Error = Error>
+    /// Fails with `ErrorKind::Unauthorized` unless the client authenticates with an
+    /// access token, for requests imgur would answer with a 401 otherwise.
+    fn require_bearer(&self) -> Result<()> {
+        match self.auth {
+            Auth::Bearer(_) => Ok(()),
+            Auth::ClientId(_) => Err(ErrorKind::Unauthorized.into()),
+        }
+    }
+
+    fn common_headers(&self) -> CommonHeaders {
+        CommonHeaders {
+            authorization: self.auth.header_value(),
+            accept_language: self.accept_language.clone(),
+            extra: self.extra_headers.clone(),
+        }
+    }
+
+    /// Stops the client from starting new requests and resolves once the requests in
+    /// flight have finished, e.g. so that a server doesn't cut off uploads when it's
+    /// shut down. Requests started afterwards fail with `ErrorKind::Draining`.
+    ///
+    /// All clones of a client share the count of requests in flight, so draining one
+    /// clone drains all of them. A request counts as in flight from the call that
+    /// creates its future until that future finishes or is dropped, including any
+    /// retries. Streams from `album_images_streaming` count from when they're first
+    /// polled until they end.
+    pub fn drain(&self) -> Drain {
+        drain::Requests::drain(&self.requests)
+    }
+
+    /// Counts `work` as a request in flight until it finishes, or fails right away if
+    /// the client is draining.
+    fn tracked<F>(&self, work: F) -> impl Future<Item = F::Item, Error = Error>
     where
-        T: DeserializeOwned,
+        F: Future<Error = Error>,
     {
-        let mut request = Request::new(Method::Get, url);
-        request
-            .headers_mut()
-            .set(Authorization(format!("Client-ID {}", self.client_id)));
-
-        self.client
-            .request(request)
-            .map_err(Error::from)
-            .and_then(|resp| {
-                resp.body().map_err(Error::from).concat2().and_then(|body| {
-                    future::result(serde_json::from_slice(&body).map_err(Error::from))
-                })
-            })
+        match drain::Requests::start(&self.requests) {
+            Ok(guard) => Either::A(drain::Tracked::new(work, guard)),
+            Err(e) => Either::B(future::err(e)),
+        }
     }
 
-    /// Gets data for an image (`GET /image/<id>`)
-    pub fn image(&self, id: &str) -> impl Future<Item = Response<Image>, Error = Error> {
-        let url = format!("{}/image/{}", API, id).parse().unwrap();
-        self.get_with_header(url)
+    /// Like `tracked`, for streams.
+    fn tracked_stream<S>(&self, work: S) -> impl Stream<Item = S::Item, Error = Error>
+    where
+        S: Stream<Error = Error>,
+    {
+        future::result(drain::Requests::start(&self.requests))
+            .map(move |guard| drain::Tracked::new(work, guard))
+            .flatten_stream()
+    }
+
+    /// Answers `GET url` from the response cache if possible, making the request and
+    /// caching its response otherwise.
+    #[cfg(feature = "cache")]
+    fn cached_get<T>(&self, id: &str, url: Uri) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: cache::Cacheable + DeserializeOwned,
+    {
+        let cache = self.cache.clone();
+        let id = id.to_string();
+        if let Some(hit) = cache.as_ref().and_then(|cache| cache.get(&id)) {
+            return Either::A(future::ok(hit));
+        }
+        Either::B(self.get_with_header(url).map(move |resp| {
+            if let Some(cache) = cache {
+                cache.insert(&id, &resp);
+            }
+            resp
+        }))
     }
 
-    /// Get data for an album (`GET /albuim/<id>`)
-    pub fn album(&self, album_id: &str) -> impl Future<Item = Response<Album>, Error = Error> {
-        let url = format!("{}/album/{}", API, album_id).parse().unwrap();
+    #[cfg(not(feature = "cache"))]
+    fn cached_get<T>(&self, _id: &str, url: Uri) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
         self.get_with_header(url)
     }
 
-    /// Gets data for all the images in an album. (`GET /album/<album_id>/images`).
-    pub fn album_images(
+    /// A `Paginator` for the `*_stream` methods, with the client's `max_pages`.
+    fn paginator<T, F, R>(&self, fetch: F) -> Paginator<T, F, R>
+    where
+        F: FnMut(u32) -> R,
+        R: Future<Item = Response<Vec<T>>, Error = Error>,
+    {
+        let paginator = Paginator::new(fetch);
+        match self.max_pages {
+            Some(max_pages) => paginator.max_pages(max_pages),
+            None => paginator,
+        }
+    }
+
+    /// Drops the cached responses about the image `id` once `work`, which changes the
+    /// image, is done.
+    #[cfg(feature = "cache")]
+    fn forget_image_after<F>(&self, id: &str, work: F) -> impl Future<Item = F::Item, Error = Error>
+    where
+        F: Future<Error = Error>,
+    {
+        let cache = self.cache.clone();
+        let id = id.to_string();
+        work.then(move |result| {
+            if let Some(cache) = cache {
+                cache.forget_image(&id);
+            }
+            result
+        })
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn forget_image_after<F>(
         &self,
-        album_id: &str,
-    ) -> impl Future<Item = Response<Vec<Image>>, Error = Error> {
-        let url = format!("{}/album/{}/images", API, album_id)
-            .parse()
-            .unwrap();
-        self.get_with_header(url)
+        _id: &str,
+        work: F,
+    ) -> impl Future<Item = F::Item, Error = Error>
+    where
+        F: Future<Error = Error>,
+    {
+        work
     }
-}
 
-/// Wrapper type returned from all the web API methods.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Response<T> {
-    /// HTTP status of the response.
-    pub status: usize,
-    /// Whether the response succeeded.
-    pub success: bool,
-    /// Either data returned from the API or an error (see `ResponseData`)
-    pub data: ResponseData<T>,
-}
+    /// Like `forget_image_after`, for a change made with the image's id or deletehash.
+    #[cfg(feature = "cache")]
+    fn forget_deleted_image_after<F>(
+        &self,
+        id_or_hash: &str,
+        work: F,
+    ) -> impl Future<Item = F::Item, Error = Error>
+    where
+        F: Future<Error = Error>,
+    {
+        let cache = self.cache.clone();
+        let id_or_hash = id_or_hash.to_string();
+        work.then(move |result| {
+            if let Some(cache) = cache {
+                cache.forget_image_or_deletehash(&id_or_hash);
+            }
+            result
+        })
+    }
 
-/// The `data` field of the JSON response can either be some data (e.g. data for an image)
-/// or an error.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum ResponseData<T> {
-    /// Successful response.
-    Success(T),
-    /// Error response.
-    Error(ApiError),
-}
+    #[cfg(not(feature = "cache"))]
+    fn forget_deleted_image_after<F>(
+        &self,
+        _id_or_hash: &str,
+        work: F,
+    ) -> impl Future<Item = F::Item, Error = Error>
+    where
+        F: Future<Error = Error>,
+    {
+        work
+    }
 
-impl<T> ResponseData<T> {
-    /// Converts `self` into a `Result`.
-    pub fn into_result(self) -> Result<T> {
-        match self {
-            ResponseData::Success(v) => Ok(v),
-            ResponseData::Error(e) => Err(e.into()),
-        }
+    fn get_with_header<T>(&self, url: Uri) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        let capture_raw = self.capture_raw;
+        let raw = match self.in_flight_gets {
+            Some(ref gets) => {
+                Either::A(gets.join(url.to_string(), || self.send_raw(Method::Get, url, None)))
+            }
+            None => Either::B(self.send_raw(Method::Get, url, None)),
+        };
+        raw.and_then(move |raw| raw.parse(capture_raw))
     }
-}
 
-/// Error data returned by the API.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ApiError {
-    /// Error description.
-    pub error: String,
-    /// Request URL.
-    pub request: String,
-    /// HTTP method used for the request.
-    pub method: String,
-}
+    fn post_with_header<T>(
+        &self,
+        url: Uri,
+        body: RequestBody,
+    ) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.send_with_header(Method::Post, url, Some(body))
+    }
 
-impl fmt::Display for ApiError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "Request {} {} failed: {}",
-            self.method, self.request, self.error
-        )
+    fn send_with_header<T>(
+        &self,
+        method: Method,
+        url: Uri,
+        body: Option<RequestBody>,
+    ) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        let capture_raw = self.capture_raw;
+        self.send_raw(method, url, body)
+            .and_then(move |raw| raw.parse(capture_raw))
     }
-}
 
-impl error::Error for ApiError {
-    fn description(&self) -> &str {
-        &self.error
+    /// Sends an API request, with retries and throttling, and collects the body of the
+    /// response.
+    fn send_raw(
+        &self,
+        method: Method,
+        url: Uri,
+        body: Option<RequestBody>,
+    ) -> impl Future<Item = RawBody, Error = Error> {
+        let max_body_bytes = self.max_body_bytes;
+        let work = self
+            .send_with_retries(method, url, body)
+            .and_then(move |(resp, rate_limit)| {
+                let status = resp.status().as_u16();
+                let html = is_html(resp.headers());
+                let server_timing = server_timing(resp.headers());
+                concat_limited(resp.body(), max_body_bytes).and_then(move |body| {
+                    reject_html(status, html, &body)?;
+                    Ok(RawBody {
+                        body,
+                        rate_limit,
+                        server_timing,
+                    })
+                })
+            });
+        self.tracked(work)
     }
-}
 
-/// Data returned for an image.
-#[allow(missing_docs)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Image {
-    pub account_id: Option<String>,
-    pub account_url: Option<String>,
-    pub ad_type: u32,
-    pub ad_url: String,
-    pub animated: bool,
-    pub bandwidth: u32,
-    pub datetime: u32,
-    pub description: Option<String>,
-    pub favorite: bool,
-    pub height: u32,
-    pub id: String,
-    pub in_gallery: bool,
-    pub in_most_viral: bool,
-    pub is_ad: bool,
-    pub link: String,
-    pub nsfw: Option<bool>,
-    pub section: Option<String>,
-    pub size: u32,
-    pub tags: Vec<String>,
-    pub title: Option<String>,
-    pub views: u32,
-    pub vote: Option<String>,
-    pub width: u32,
-}
+    /// Sends an API request, resolving to the first response that isn't retried along
+    /// with its rate limits. Every attempt, including retries, waits for the throttle
+    /// first. The throttle is only consulted once an attempt is about to be sent, so
+    /// futures that are dropped before they're polled don't take up a slot.
+    fn send_with_retries(
+        &self,
+        method: Method,
+        url: Uri,
+        body: Option<RequestBody>,
+    ) -> impl Future<Item = (hyper::Response, Option<RateLimitInfo>), Error = Error> {
+        let client = self.client.clone();
+        let timer = self.timer.clone();
+        let throttle = self.throttle.clone();
+        let headers = self.common_headers();
+        let retry_policy = self.retry_policy.clone();
+        let last_rate_limit = self.rate_limit.clone();
 
-#[allow(missing_docs)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Album {
-    pub id: String,
-    pub title: String,
-    pub description: String,
-    pub datetime: u32,
-    pub cover: String,
-    pub cover_width: u32,
-    pub cover_height: u32,
-    pub account_url: Option<String>,
-    pub privacy: String,
-    pub layout: String,
-    pub views: u32,
-    pub link: String,
-    pub favorite: bool,
-    pub nsfw: Option<bool>,
-    pub section: Option<String>,
-    pub order: u32,
-    pub deletehash: Option<String>,
-    pub images_count: u32,
-    pub images: Option<Vec<Image>>,
-    pub in_gallery: bool,
-}
+        future::loop_fn(0, move |attempt| {
+            let request = build_request(method.clone(), url.clone(), &headers, body.as_ref());
 
-#[cfg(test)]
-mod tests {
-    use tokio_core::reactor::Core;
+            let client = client.clone();
+            let retry_policy = retry_policy.clone();
+            let timer = timer.clone();
+            let throttle = throttle.clone();
+            let last_rate_limit = last_rate_limit.clone();
+            throttled(timer.clone(), throttle, last_rate_limit.clone())
+                .and_then(move |()| client.request(request).map_err(transport_error))
+                .and_then(move |resp| {
+                    let rate_limit = RateLimitInfo::from_headers(resp.headers());
+                    if rate_limit.is_some() {
+                        *last_rate_limit.lock().unwrap() = rate_limit;
+                    }
+                    if attempt < retry_policy.max_retries() && retry::should_retry(resp.status()) {
+                        let delay = retry_policy.delay(attempt);
+                        debug!(
+                            "Request failed with {}, retrying in {:?}",
+                            resp.status(),
+                            delay
+                        );
+                        let retry = timer
+                            .delay(delay)
+                            .map(move |()| Loop::Continue(attempt + 1));
+                        Either::A(retry)
+                    } else {
+                        Either::B(future::ok(Loop::Break((resp, rate_limit))))
+                    }
+                })
+        })
+    }
 
-    use super::*;
+    /// Sends `GET <api>/<path>` for an endpoint the crate doesn't wrap yet, e.g.
+    /// `get_raw::<serde_json::Value>("gallery/hot/viral/0")`. The client's
+    /// authentication, retries and other settings apply as usual, and the response is
+    /// parsed into `T`.
+    ///
+    /// This is a lower-level escape hatch: `path` is used as is, so any user input in
+    /// it has to be percent-encoded by the caller. An invalid path fails with a
+    /// `hyper` URI error.
+    pub fn get_raw<T>(&self, path: &str) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        match raw_url(path) {
+            Ok(url) => Either::A(self.get_with_header(url)),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
 
-    const CLIENT_ID: &str = include_str!("client_id.txt");
+    /// Sends `POST <api>/<path>` with `params` as a form, like `get_raw`.
+    pub fn post_raw<T>(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> impl Future<Item = Response<T>, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        match raw_url(path) {
+            Ok(url) => Either::A(self.post_with_header(url, RequestBody::urlencoded(params))),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
 
-    #[test]
-    fn get_image() {
-        let mut core = Core::new().unwrap();
-        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
-        let id = "PE2NI";
-        let work = api.image(id);
-        let resp = core.run(work).unwrap();
-        assert_eq!(resp.data.into_result().unwrap().id, id);
+    /// Checks whether imgur accepts the client's credentials, with a cheap request to
+    /// `GET /credits`. Resolves to `false` if imgur rejects them (401 or 403), so that
+    /// misconfigured tools can fail fast; other failures are still errors.
+    pub fn validate(&self) -> impl Future<Item = bool, Error = Error> {
+        let url = format!("{}/credits", API).parse().unwrap();
+        self.get_with_header(url).and_then(credentials_accepted)
     }
 
-    #[test]
-    fn get_album_images() {
-        let mut core = Core::new().unwrap();
-        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
-        let id = "cXz3n";
-        let work = api.album_images(id);
-        let resp = core.run(work).unwrap();
-        let images: Vec<Image> = resp.data.into_result().unwrap();
-        assert!(images.len() > 5);
+    /// Gets the credits the user and the application have left (`GET /credits`). This
+    /// request itself doesn't cost any credits.
+    pub fn credits(&self) -> impl Future<Item = Response<Credits>, Error = Error> {
+        let url = format!("{}/credits", API).parse().unwrap();
+        self.get_with_header(url)
     }
 
-    #[test]
-    fn get_error() {
-        let mut core = Core::new().unwrap();
-        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
-        let id = "cXz";
-        let work = api.album_images(id);
-        let resp = core.run(work).unwrap();
-        assert!(resp.data.into_result().is_err());
+    /// Gets data for an image (`GET /image/<id>`)
+    pub fn image<I>(&self, id: &I) -> impl Future<Item = Response<Image>, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        let id = id.as_image_id();
+        self.cached_get(id, image_url(id).parse().unwrap())
     }
 
-    #[test]
-    fn get_album() {
-        let mut core = Core::new().unwrap();
-        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
-        let id = "cXz3n";
-        let work = api.album(id);
-        let resp = core.run(work).unwrap();
-        assert_eq!(resp.data.into_result().unwrap().id, "cXz3n");
+    /// Builds the request `image` would send for `id`, without sending it, e.g. to
+    /// compare the URL against imgur's documentation. The credentials in the
+    /// `Authorization` header are replaced with `<redacted>`, so the request can be
+    /// logged or pasted into a bug report.
+    pub fn debug_request_for_image<I>(&self, id: &I) -> Request
+    where
+        I: AsImageId + ?Sized,
+    {
+        let url = image_url(id.as_image_id()).parse().unwrap();
+        let mut request = Request::new(Method::Get, url);
+        let headers = CommonHeaders {
+            authorization: self.auth.redacted_header_value(),
+            ..self.common_headers()
+        };
+        headers.apply(request.headers_mut());
+        request
+    }
+
+    /// Get data for an album (`GET /album/<id>`)
+    ///
+    /// imgur includes all of the album's images in `Album::images`, so for large albums
+    /// the response is large too; see `album_meta` if you only need the metadata.
+    pub fn album<I>(&self, album_id: &I) -> impl Future<Item = Response<Album>, Error = Error>
+    where
+        I: AsAlbumId + ?Sized,
+    {
+        let album_id = album_id.as_album_id();
+        let url = format!("{}/album/{}", API, form::percent_encode(album_id))
+            .parse()
+            .unwrap();
+        self.cached_get(album_id, url)
+    }
+
+    /// Like `album`, but drops the images imgur sends along, leaving `images` as
+    /// `None`. The response is still downloaded and parsed in full, but the images are
+    /// freed right away, which helps when holding on to many albums.
+    pub fn album_meta<I>(&self, album_id: &I) -> impl Future<Item = Response<Album>, Error = Error>
+    where
+        I: AsAlbumId + ?Sized,
+    {
+        self.album(album_id).map(|mut resp| {
+            if let ResponseData::Success(ref mut album) = resp.data {
+                album.images = None;
+            }
+            resp
+        })
+    }
+
+    /// Gets data for one of an album's images (`GET /album/<album_id>/image/<id>`), as
+    /// it appears in the album. Unlike `image`, this fails if the image isn't part of
+    /// the album.
+    pub fn album_image<A, I>(
+        &self,
+        album_id: &A,
+        image_id: &I,
+    ) -> impl Future<Item = Response<Image>, Error = Error>
+    where
+        A: AsAlbumId + ?Sized,
+        I: AsImageId + ?Sized,
+    {
+        let url = format!(
+            "{}/album/{}/image/{}",
+            API,
+            form::percent_encode(album_id.as_album_id()),
+            form::percent_encode(image_id.as_image_id())
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets data for all the images in an album. (`GET /album/<album_id>/images`).
+    pub fn album_images<I>(
+        &self,
+        album_id: &I,
+    ) -> impl Future<Item = Response<Vec<Image>>, Error = Error>
+    where
+        I: AsAlbumId + ?Sized,
+    {
+        let url = format!(
+            "{}/album/{}/images",
+            API,
+            form::percent_encode(album_id.as_album_id())
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+            .map(|mut resp: Response<Vec<Image>>| {
+                if let ResponseData::Success(ref mut images) = resp.data {
+                    number_images(images);
+                }
+                resp
+            })
+    }
+
+    /// Like `album_images`, but parses the response as it arrives and yields each image
+    /// as soon as it's complete, instead of buffering the whole body first. Meant for
+    /// albums with thousands of images; for everything else `album_images` is simpler.
+    ///
+    /// The request is throttled, retried and counted towards `rate_limit` like any
+    /// other, but only until the response arrives: once images may have been yielded,
+    /// failures aren't retried. As there's no `Response`, `capture_raw` doesn't apply,
+    /// and the body isn't limited by `max_body_bytes` since it isn't buffered. An HTML
+    /// error page fails with `ErrorKind::ServiceUnavailable` if it's sent as
+    /// `text/html`, and with a parse error otherwise.
+    pub fn album_images_streaming<I>(
+        &self,
+        album_id: &I,
+    ) -> impl Stream<Item = Image, Error = Error>
+    where
+        I: AsAlbumId + ?Sized,
+    {
+        let url = format!(
+            "{}/album/{}/images",
+            API,
+            form::percent_encode(album_id.as_album_id())
+        )
+        .parse()
+        .unwrap();
+        self.stream_data_array(url).zip(stream::iter_ok(0..)).map(
+            |(mut image, position): (Image, u32)| {
+                image.position = Some(position);
+                image
+            },
+        )
+    }
+
+    /// Sends `GET url` and parses the response's `data` array as it arrives, for the
+    /// `*_streaming` methods.
+    fn stream_data_array<T>(&self, url: Uri) -> impl Stream<Item = T, Error = Error>
+    where
+        T: DeserializeOwned,
+    {
+        let max_body_bytes = self.max_body_bytes;
+        let mut parser = streaming::DataArrayParser::new();
+        let work = self
+            .send_with_retries(Method::Get, url, None)
+            .map(
+                move |(resp, _)| -> Box<dyn Stream<Item = Chunk, Error = Error>> {
+                    let status = resp.status().as_u16();
+                    if is_html(resp.headers()) {
+                        // An error page rather than JSON; read it for the error message.
+                        let error = concat_limited(resp.body(), max_body_bytes)
+                            .and_then(move |body| reject_html(status, true, &body))
+                            .map(|()| stream::empty())
+                            .flatten_stream();
+                        Box::new(error)
+                    } else {
+                        Box::new(resp.body().map_err(transport_error))
+                    }
+                },
+            )
+            .flatten_stream()
+            .map(Some)
+            .chain(stream::once(Ok(None)))
+            .and_then(move |chunk| match chunk {
+                Some(chunk) => parser.feed(&chunk),
+                None => parser.finish(),
+            })
+            .map(stream::iter_ok)
+            .flatten();
+        self.tracked_stream(work)
+    }
+
+    /// Creates an album (`POST /album`). Anonymous albums (with just a client id) can
+    /// only be changed later with the `deletehash` in the response, and can only be
+    /// created from images passed by their deletehashes.
+    ///
+    /// Fails with `ErrorKind::InvalidInput`, without making a request, if the title or
+    /// description is longer than `MAX_TITLE_LENGTH` or `MAX_DESCRIPTION_LENGTH`.
+    pub fn create_album(
+        &self,
+        options: AlbumOptions,
+    ) -> impl Future<Item = Response<AlbumCreateResponse>, Error = Error> {
+        if let Err(e) = options.validate() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/album", API).parse().unwrap();
+        Either::B(self.post_with_header(url, options.form()))
+    }
+
+    /// Uploads an image (`POST /image`). Fails with `ErrorKind::InvalidInput`, without
+    /// making a request, if the title or description is longer than `MAX_TITLE_LENGTH`
+    /// or `MAX_DESCRIPTION_LENGTH`.
+    pub fn upload_image(
+        &self,
+        bytes: &[u8],
+        options: UploadOptions,
+    ) -> impl Future<Item = Response<Image>, Error = Error> {
+        if let Err(e) = options.validate() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/image", API).parse().unwrap();
+        Either::B(self.post_with_header(url, options.form("image", bytes)))
+    }
+
+    /// Uploads a video (`POST /upload`), e.g. an MP4 or WebM file. imgur converts it
+    /// and the response's `mp4` and `gifv` fields link to the results; they may only
+    /// work once processing has finished. Videos over imgur's size or length limits
+    /// come back as an error response with imgur's reason.
+    ///
+    /// Fails without making a request for overlong titles or descriptions, like
+    /// `upload_image`.
+    pub fn upload_video(
+        &self,
+        bytes: &[u8],
+        options: UploadOptions,
+    ) -> impl Future<Item = Response<Image>, Error = Error> {
+        if let Err(e) = options.validate() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/upload", API).parse().unwrap();
+        Either::B(self.post_with_header(url, options.form("video", bytes)))
+    }
+
+    /// Downloads the raw bytes behind `url`, e.g. an image's `link`. This talks to
+    /// imgur's CDN directly and doesn't use any API credits.
+    ///
+    /// Redirects are followed up to the client's `max_redirects`, failing with
+    /// `ErrorKind::RedirectLoop` if a URL comes up twice.
+    pub fn download(&self, url: &str) -> impl Future<Item = Vec<u8>, Error = Error> {
+        let client = self.client.clone();
+        let max_redirects = self.max_redirects;
+        let work = future::result(
+            url.parse::<Uri>()
+                .map_err(|e| Error::from(hyper::Error::from(e))),
+        )
+        .and_then(move |uri| {
+            future::loop_fn(
+                (uri, Vec::new()),
+                move |(uri, mut visited): (Uri, Vec<Uri>)| {
+                    client
+                        .get(uri.clone())
+                        .map_err(transport_error)
+                        .and_then(move |resp| {
+                            let status = resp.status();
+                            if status.is_success() {
+                                let body = resp
+                                    .body()
+                                    .concat2()
+                                    .map_err(transport_error)
+                                    .map(|body| Loop::Break(body.to_vec()));
+                                return Either::A(body);
+                            }
+                            let next = match redirect_target(&uri, &resp) {
+                                None => {
+                                    Err(ErrorKind::DownloadFailed(uri.to_string(), status.as_u16())
+                                        .into())
+                                }
+                                Some(Err(e)) => Err(e),
+                                Some(Ok(next)) => {
+                                    visited.push(uri);
+                                    if visited.contains(&next) {
+                                        Err(ErrorKind::RedirectLoop(next.to_string()).into())
+                                    } else if visited.len() > max_redirects as usize {
+                                        let url = visited[0].to_string();
+                                        Err(ErrorKind::TooManyRedirects(url, max_redirects).into())
+                                    } else {
+                                        debug!("Download redirected to {}", next);
+                                        Ok(Loop::Continue((next, visited)))
+                                    }
+                                }
+                            };
+                            Either::B(future::result(next))
+                        })
+                },
+            )
+        });
+        self.tracked(work)
+    }
+
+    /// Downloads an image from imgur's CDN in the given size, without asking the API
+    /// for its link first, so it doesn't use any credits. `id` can be anything
+    /// `parse_id` understands. The file extension is taken from `id` if it has one
+    /// (`abc123.png`), and defaults to `.jpg` otherwise; imgur serves the image either
+    /// way. Thumbnails are always JPEGs.
+    pub fn download_by_id(
+        &self,
+        id: &str,
+        size: Size,
+    ) -> impl Future<Item = Vec<u8>, Error = Error> {
+        match cdn_url(id, size) {
+            Ok(url) => Either::A(self.download(&url)),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
+
+    /// Downloads all the images in an album, with at most `max_concurrency` downloads
+    /// in flight at once. Failing to fetch the album's image list fails the whole
+    /// future, but failed image downloads are only listed in the report, in album
+    /// order, next to the successful ones.
+    ///
+    /// The report holds every downloaded file in memory until it's dropped, so for
+    /// very large albums, `download` images one by one instead.
+    pub fn download_album<I>(
+        &self,
+        album_id: &I,
+        max_concurrency: usize,
+    ) -> impl Future<Item = AlbumDownloadReport, Error = Error>
+    where
+        I: AsAlbumId + ?Sized,
+    {
+        let client = self.clone();
+        let cost = estimated_cost(Endpoint::Get);
+        let images = self.album_images(album_id).into_data();
+        future::result(self.check_budget(u64::from(cost)))
+            .and_then(move |()| images)
+            .and_then(move |images| {
+                let downloads = images.into_iter().map(move |image| {
+                    client
+                        .download(&image.link)
+                        .then(move |bytes| Ok((image, bytes)))
+                });
+                run_bounded(downloads, max_concurrency).map(AlbumDownloadReport::new)
+            })
+    }
+
+    /// Deletes an image (`DELETE /image/<deletehash>`). Anonymous uploads can only be
+    /// deleted with their `deletehash`.
+    ///
+    /// With the `cache` feature, this drops the image and the albums containing it from
+    /// the cache. Images cached without their deletehash can't be told apart, so if no
+    /// cached image has `delete_hash`, the whole cache is dropped.
+    pub fn delete_image(
+        &self,
+        delete_hash: &str,
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        self.delete_image_at(API, delete_hash)
+    }
+
+    /// `delete_image` against the API at `base`.
+    fn delete_image_at(
+        &self,
+        base: &str,
+        delete_hash: &str,
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        let url = format!("{}/image/{}", base, form::percent_encode(delete_hash))
+            .parse()
+            .unwrap();
+        let work = self.send_with_header(Method::Delete, url, None);
+        self.forget_deleted_image_after(delete_hash, work)
+    }
+
+    /// Deletes many images by their deletehashes, with at most `max_concurrency`
+    /// requests in flight at once. Every deletion gets its own result, in the order of
+    /// `delete_hashes`, so one failure doesn't abort the rest.
+    pub fn delete_images(
+        &self,
+        delete_hashes: &[&str],
+        max_concurrency: usize,
+    ) -> impl Future<Item = Vec<Result<()>>, Error = Error> {
+        let cost = u64::from(estimated_cost(Endpoint::Post)) * delete_hashes.len() as u64;
+        if let Err(e) = self.check_budget(cost) {
+            return Either::A(future::err(e));
+        }
+        let deletions: Vec<_> = delete_hashes
+            .iter()
+            .map(|hash| {
+                self.delete_image(hash)
+                    .then(|result| Ok(result.and_then(|resp| resp.data.into_result()).map(|_| ())))
+            })
+            .collect();
+        Either::B(run_bounded(deletions, max_concurrency))
+    }
+
+    /// Favorites an image, or unfavorites it if the user already favorited it
+    /// (`POST /image/<id>/favorite`). Requires an access token (`Auth::Bearer`).
+    ///
+    /// The response data is `"favorited"` or `"unfavorited"`. To get a particular state
+    /// regardless of the current one, use `ensure_favorited` or `ensure_unfavorited`.
+    pub fn favorite_image<I>(&self, id: &I) -> impl Future<Item = Response<String>, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let id = id.as_image_id();
+        let url = format!("{}/favorite", image_url(id)).parse().unwrap();
+        let work = self.send_with_header(Method::Post, url, None);
+        Either::B(self.forget_image_after(id, work))
+    }
+
+    /// Makes sure the user has favorited an image, returning whether it had to be
+    /// favorited. Requires an access token (`Auth::Bearer`).
+    ///
+    /// This fetches the image first (bypassing the response cache) to check its
+    /// `favorite` field, so it costs two requests when the image is toggled and one
+    /// when it's already favorited.
+    pub fn ensure_favorited<I>(&self, id: &I) -> impl Future<Item = bool, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        self.set_favorite(id.as_image_id(), true)
+    }
+
+    /// Makes sure the user hasn't favorited an image, returning whether it had to be
+    /// unfavorited. Like `ensure_favorited`, this costs an extra request for checking
+    /// the current state.
+    pub fn ensure_unfavorited<I>(&self, id: &I) -> impl Future<Item = bool, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        self.set_favorite(id.as_image_id(), false)
+    }
+
+    fn set_favorite(&self, id: &str, favorite: bool) -> impl Future<Item = bool, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let client = self.clone();
+        let id = id.to_string();
+        let current = self.get_with_header(image_url(&id).parse().unwrap());
+        Either::B(current.into_data().and_then(move |image: Image| {
+            if image.favorite == favorite {
+                Either::A(future::ok(false))
+            } else {
+                Either::B(client.favorite_image(id.as_str()).into_data().map(|_| true))
+            }
+        }))
+    }
+
+    /// Finds out whether `id` refers to an image or an album, for ids pasted without any
+    /// context. This asks the image endpoint first and falls back to the album endpoint
+    /// on a 404, so it costs up to two requests.
+    pub fn kind_of(&self, id: &str) -> impl Future<Item = ResourceKind, Error = Error> {
+        let client = self.clone();
+        let id = id.to_string();
+        self.image(id.as_str()).and_then(move |resp| {
+            if resp.success {
+                Either::A(future::ok(ResourceKind::Image))
+            } else if resp.status == 404 {
+                Either::B(
+                    client
+                        .album(id.as_str())
+                        .into_data()
+                        .map(|_| ResourceKind::Album),
+                )
+            } else {
+                Either::A(future::result(
+                    resp.data.into_result().map(|_| ResourceKind::Image),
+                ))
+            }
+        })
+    }
+
+    /// Gets a page of images uploaded by an account
+    /// (`GET /account/<username>/images/<page>`). Requires an access token, and
+    /// `username` is usually `"me"`.
+    pub fn account_images(
+        &self,
+        username: &str,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<Image>>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let username = normalize_username(username);
+        let url = format!(
+            "{}/account/{}/images/{}",
+            API,
+            form::percent_encode(&username),
+            page
+        )
+        .parse()
+        .unwrap();
+        Either::B(self.get_with_header(url))
+    }
+
+    /// Gets the number of images uploaded by an account
+    /// (`GET /account/<username>/images/count`), e.g. to know how many pages
+    /// `account_images` has (50 images each) before walking them. `username` `"me"`
+    /// requires an access token (`Auth::Bearer`).
+    pub fn account_image_count(
+        &self,
+        username: &str,
+    ) -> impl Future<Item = Response<u32>, Error = Error> {
+        let username = normalize_username(username);
+        if username == "me" {
+            if let Err(e) = self.require_bearer() {
+                return Either::A(future::err(e));
+            }
+        }
+        let url = format!(
+            "{}/account/{}/images/count",
+            API,
+            form::percent_encode(&username)
+        )
+        .parse()
+        .unwrap();
+        Either::B(self.get_with_header(url))
+    }
+
+    /// Like `account_images`, but also tells whether there may be more pages.
+    pub fn account_images_page(
+        &self,
+        username: &str,
+        page: u32,
+    ) -> impl Future<Item = Page<Image>, Error = Error> {
+        pagination::page(
+            self.account_images(username, page),
+            ACCOUNT_IMAGES_PAGE_SIZE,
+        )
+    }
+
+    /// Streams all the images uploaded by an account, walking
+    /// `/account/<username>/images/<page>` until a page comes back empty. Pages are
+    /// fetched as the stream is polled; configure a `RetryPolicy` to back off when the
+    /// rate limit is hit in the middle.
+    pub fn account_images_stream(
+        &self,
+        username: &str,
+    ) -> impl Stream<Item = Image, Error = Error> {
+        let client = self.clone();
+        let username = username.to_string();
+        self.paginator(move |page| client.account_images(&username, page))
+    }
+
+    /// Gets one of an account's images (`GET /account/<username>/image/<id>`). Fetched
+    /// by its owner with an access token (`Auth::Bearer`), the image includes fields
+    /// only the owner sees, such as its `deletehash`. `username` `"me"` requires an
+    /// access token.
+    pub fn account_image<I>(
+        &self,
+        username: &str,
+        image_id: &I,
+    ) -> impl Future<Item = Response<Image>, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        let username = normalize_username(username);
+        if username == "me" {
+            if let Err(e) = self.require_bearer() {
+                return Either::A(future::err(e));
+            }
+        }
+        let url = format!(
+            "{}/account/{}/image/{}",
+            API,
+            form::percent_encode(&username),
+            form::percent_encode(image_id.as_image_id())
+        )
+        .parse()
+        .unwrap();
+        Either::B(self.get_with_header(url))
+    }
+
+    /// Gets an account's public profile (`GET /account/<username>`).
+    pub fn account(&self, username: &str) -> impl Future<Item = Response<Account>, Error = Error> {
+        let username = normalize_username(username);
+        let url = format!("{}/account/{}", API, form::percent_encode(&username))
+            .parse()
+            .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets a page of the posts an account has submitted to the gallery
+    /// (`GET /account/<username>/submissions/<page>`), as opposed to all of its uploads.
+    pub fn account_submissions(
+        &self,
+        username: &str,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        let username = normalize_username(username);
+        let url = format!(
+            "{}/account/{}/submissions/{}",
+            API,
+            form::percent_encode(&username),
+            page
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the trophies shown on an account's profile. imgur has no endpoint for just
+    /// the trophies, so this gets them from `GET /account/<username>/gallery_profile`.
+    pub fn account_trophies(
+        &self,
+        username: &str,
+    ) -> impl Future<Item = Response<Vec<Trophy>>, Error = Error> {
+        let username = normalize_username(username);
+        let url = format!(
+            "{}/account/{}/gallery_profile",
+            API,
+            form::percent_encode(&username)
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+            .map(|resp: Response<GalleryProfile>| resp.map_data(|profile| profile.trophies))
+    }
+
+    /// Gets a page of everything an account has favorited
+    /// (`GET /account/<username>/favorites/<page>/<sort>`), including images that
+    /// were never posted to the gallery. `username` `"me"` requires an access token
+    /// (`Auth::Bearer`).
+    pub fn account_favorites(
+        &self,
+        username: &str,
+        page: u32,
+        sort: FavoritesSort,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        let username = normalize_username(username);
+        if username == "me" {
+            if let Err(e) = self.require_bearer() {
+                return Either::A(future::err(e));
+            }
+        }
+        let url = format!(
+            "{}/account/{}/favorites/{}/{}",
+            API,
+            form::percent_encode(&username),
+            page,
+            sort
+        )
+        .parse()
+        .unwrap();
+        Either::B(self.get_with_header(url))
+    }
+
+    /// Gets an account's folders (`GET /account/<username>/folders`). Private folders
+    /// are only included with an access token (`Auth::Bearer`) for that account.
+    pub fn account_folders(
+        &self,
+        username: &str,
+    ) -> impl Future<Item = Response<Vec<Folder>>, Error = Error> {
+        let username = normalize_username(username);
+        let url = format!(
+            "{}/account/{}/folders",
+            API,
+            form::percent_encode(&username)
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Changes the current user's account settings (`POST /account/me/settings`). Only
+    /// the fields set in `options` are changed. Requires an access token
+    /// (`Auth::Bearer`).
+    pub fn update_account_settings(
+        &self,
+        options: AccountSettingsUpdate,
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/account/me/settings", API).parse().unwrap();
+        Either::B(self.post_with_header(url, options.form()))
+    }
+
+    /// Gets a page of the posts in one of an account's folders
+    /// (`GET /account/<username>/folders/<folder_id>/favorites/<page>`). See
+    /// `account_folders` for the folder ids.
+    pub fn folder_images(
+        &self,
+        username: &str,
+        folder_id: u64,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        let username = normalize_username(username);
+        let url = format!(
+            "{}/account/{}/folders/{}/favorites/{}",
+            API,
+            form::percent_encode(&username),
+            folder_id,
+            page
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Streams all the posts in one of an account's folders, page by page (see
+    /// `Paginator`).
+    pub fn folder_images_stream(
+        &self,
+        username: &str,
+        folder_id: u64,
+    ) -> impl Stream<Item = GalleryItem, Error = Error> {
+        let client = self.clone();
+        let username = username.to_string();
+        self.paginator(move |page| client.folder_images(&username, folder_id, page))
+    }
+
+    /// Gets a page of the main gallery feed
+    /// (`GET /gallery/<section>/<sort>/<window>/<page>`). imgur ignores `window`
+    /// unless `section` is `Section::Top`.
+    pub fn gallery(
+        &self,
+        section: Section,
+        sort: Sort,
+        window: Window,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        let url = gallery_url(section, sort, window, page).parse().unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Like `gallery`, but also tells whether there may be more pages.
+    pub fn gallery_page(
+        &self,
+        section: Section,
+        sort: Sort,
+        window: Window,
+        page: u32,
+    ) -> impl Future<Item = Page<GalleryItem>, Error = Error> {
+        pagination::page(self.gallery(section, sort, window, page), GALLERY_PAGE_SIZE)
+    }
+
+    /// Streams a gallery feed, page by page (see `Paginator`). The gallery changes
+    /// while it's walked, so posts can show up twice or be missed.
+    pub fn gallery_stream(
+        &self,
+        section: Section,
+        sort: Sort,
+        window: Window,
+    ) -> impl Stream<Item = GalleryItem, Error = Error> {
+        let client = self.clone();
+        self.paginator(move |page| client.gallery(section, sort, window, page))
+    }
+
+    /// Gets a page of the most viral posts, as on imgur's front page
+    /// (`GET /gallery/hot/viral/day/<page>`). The feed can contain other posts too;
+    /// see `retain_most_viral` to drop them.
+    pub fn viral_gallery(
+        &self,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        self.gallery(Section::Hot, Sort::Viral, Window::Day, page)
+    }
+
+    /// Gets a page of the gallery of a subreddit
+    /// (`GET /gallery/r/<subreddit>/<sort>/<window>/<page>`), e.g. for the
+    /// `section_name` of an image. imgur ignores `window` unless `sort` is `Sort::Top`.
+    pub fn subreddit_gallery(
+        &self,
+        subreddit: &str,
+        sort: Sort,
+        window: Window,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryImage>>, Error = Error> {
+        let url = subreddit_gallery_url(subreddit, sort, window, page)
+            .parse()
+            .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Like `subreddit_gallery`, but also tells whether there may be more pages.
+    pub fn subreddit_gallery_page(
+        &self,
+        subreddit: &str,
+        sort: Sort,
+        window: Window,
+        page: u32,
+    ) -> impl Future<Item = Page<GalleryImage>, Error = Error> {
+        let fetch = self.subreddit_gallery(subreddit, sort, window, page);
+        pagination::page(fetch, GALLERY_PAGE_SIZE)
+    }
+
+    /// Gets a page of random gallery items (`GET /gallery/random/random/<page>`).
+    pub fn random_gallery(
+        &self,
+        page: u32,
+    ) -> impl Future<Item = Response<Vec<GalleryItem>>, Error = Error> {
+        let url = format!("{}/gallery/random/random/{}", API, page)
+            .parse()
+            .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the gallery post for an image (`GET /gallery/image/<id>`), with its votes,
+    /// favorite count and so on.
+    pub fn gallery_image<I>(
+        &self,
+        id: &I,
+    ) -> impl Future<Item = Response<GalleryImage>, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        let url = format!(
+            "{}/gallery/image/{}",
+            API,
+            form::percent_encode(id.as_image_id())
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the default list of featured and trending gallery tags (`GET /tags`).
+    pub fn default_gallery_tags(&self) -> impl Future<Item = Response<TagList>, Error = Error> {
+        let url = format!("{}/tags", API).parse().unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the default gallery topics (`GET /topics/defaults`). Posts can be filed
+    /// under one with `GallerySubmitOptions::topic`.
+    pub fn topics(&self) -> impl Future<Item = Response<Vec<Topic>>, Error = Error> {
+        let url = format!("{}/topics/defaults", API).parse().unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the tags applied to a gallery post and their votes
+    /// (`GET /gallery/<gallery_id>/tags`).
+    pub fn gallery_item_tags(
+        &self,
+        gallery_id: &str,
+    ) -> impl Future<Item = Response<TagVotes>, Error = Error> {
+        let url = format!("{}/gallery/{}/tags", API, form::percent_encode(gallery_id))
+            .parse()
+            .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Gets the number of comments on a gallery post
+    /// (`GET /gallery/image/<gallery_id>/comments/count`), without fetching them.
+    pub fn comment_count(
+        &self,
+        gallery_id: &str,
+    ) -> impl Future<Item = Response<u32>, Error = Error> {
+        let url = format!(
+            "{}/gallery/image/{}/comments/count",
+            API,
+            form::percent_encode(gallery_id)
+        )
+        .parse()
+        .unwrap();
+        self.get_with_header(url)
+    }
+
+    /// Votes on a gallery post (`POST /gallery/<gallery_id>/vote/<vote>`). Requires an
+    /// access token (`Auth::Bearer`).
+    ///
+    /// imgur treats a second vote in the same direction as taking the first one back,
+    /// and `Vote::Veto` removes any vote; `Vote::after` works out where that leaves
+    /// the user's vote.
+    pub fn vote(
+        &self,
+        gallery_id: &str,
+        vote: Vote,
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = vote_url(gallery_id, vote).parse().unwrap();
+        let work = self.send_with_header(Method::Post, url, None);
+        Either::B(self.forget_image_after(gallery_id, work))
+    }
+
+    /// Adds tags to a gallery post (`POST /gallery/tags/<gallery_id>`). Requires an
+    /// access token (`Auth::Bearer`).
+    pub fn add_gallery_tags(
+        &self,
+        gallery_id: &str,
+        tags: &[&str],
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/gallery/tags/{}", API, form::percent_encode(gallery_id))
+            .parse()
+            .unwrap();
+        Either::B(self.post_with_header(url, gallery_tags_form(tags)))
+    }
+
+    /// Shares an image with the community gallery under `title`
+    /// (`POST /gallery/image/<id>`). Requires an access token (`Auth::Bearer`).
+    ///
+    /// imgur rejects the submission if the user hasn't accepted the gallery terms yet,
+    /// unless `GallerySubmitOptions::bypass_terms` is set.
+    pub fn submit_to_gallery<I>(
+        &self,
+        id: &I,
+        title: &str,
+        options: GallerySubmitOptions,
+    ) -> impl Future<Item = Response<bool>, Error = Error>
+    where
+        I: AsImageId + ?Sized,
+    {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!(
+            "{}/gallery/image/{}",
+            API,
+            form::percent_encode(id.as_image_id())
+        )
+        .parse()
+        .unwrap();
+        let work = self.post_with_header(url, options.form(title));
+        Either::B(self.forget_image_after(id.as_image_id(), work))
+    }
+
+    /// Gets data for an image given its id or any common imgur URL pointing to it
+    /// (see `parse_id`).
+    pub fn image_from_url(
+        &self,
+        input: &str,
+    ) -> impl Future<Item = Response<Image>, Error = Error> {
+        match expect_kind(input, ResourceKind::Image) {
+            Ok(id) => Either::A(self.image(id.as_str())),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
+
+    /// Gets data for an album given its id or any common imgur URL pointing to it
+    /// (see `parse_id`).
+    pub fn album_from_url(
+        &self,
+        input: &str,
+    ) -> impl Future<Item = Response<Album>, Error = Error> {
+        match expect_kind(input, ResourceKind::Album) {
+            Ok(id) => Either::A(self.album(id.as_str())),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
+}
+
+/// How requests are authenticated.
+///
+/// The `Debug` output leaves out the client id or token, so that clients and builders
+/// can be logged without leaking credentials.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// Anonymous access, identifying the application by its client id.
+    ClientId(String),
+    /// Access on behalf of a user, with an OAuth2 access token. Required for endpoints
+    /// that act on an account. The `oauth` module helps with getting one.
+    Bearer(String),
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Auth::ClientId(_) => f.write_str("Client-ID(***)"),
+            Auth::Bearer(_) => f.write_str("Bearer(***)"),
+        }
+    }
+}
+
+impl Auth {
+    fn header_value(&self) -> String {
+        match *self {
+            Auth::ClientId(ref client_id) => format!("Client-ID {}", client_id),
+            Auth::Bearer(ref token) => format!("Bearer {}", token),
+        }
+    }
+
+    fn redacted_header_value(&self) -> String {
+        match *self {
+            Auth::ClientId(_) => "Client-ID <redacted>".to_string(),
+            Auth::Bearer(_) => "Bearer <redacted>".to_string(),
+        }
+    }
+}
+
+/// Builder for `ImgurClient`, created with `ImgurClient::builder`.
+///
+/// There is no option for HTTP/2: the `hyper` 0.11 client only speaks HTTP/1.1, and
+/// `hyper-tls` doesn't negotiate ALPN. Connections are kept alive and reused between
+/// requests, so for bulk work (`download_album`, `delete_images`) the concurrency limit
+/// is roughly the number of connections opened to imgur at once.
+///
+/// The client can't report whether a request reused a pooled connection: `hyper` 0.11
+/// picks connections internally and doesn't expose that per request. To watch for
+/// connection churn, enable debug logging for `hyper`, which logs every new
+/// connection.
+///
+/// `build` needs a `tokio_core` `Handle`, which runs the connections as well as the
+/// timers for retries and throttling. To run the client on a different executor,
+/// configure the `hyper::Client` with `hyper::client::Config::executor` and pass it to
+/// `build_with_timer` together with a `Timer`. That constructor is the only one that's
+/// available without the `tokio-core` feature, which is on by default.
+///
+/// The `Debug` output leaves out credentials, as for `ImgurClient`.
+#[derive(Clone)]
+pub struct ImgurClientBuilder {
+    auth: Auth,
+    retry_policy: RetryPolicy,
+    capture_raw: bool,
+    max_redirects: u32,
+    refuse_over_budget: bool,
+    accept_language: Option<String>,
+    max_body_bytes: usize,
+    throttle: Option<Throttle>,
+    extra_headers: Vec<(String, String)>,
+    coalesce_requests: bool,
+    max_pages: Option<u32>,
+    #[cfg(feature = "cache")]
+    cache: Option<(usize, Duration)>,
+    #[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+    insecure_tls: InsecureTls,
+}
+
+impl fmt::Debug for ImgurClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("ImgurClientBuilder");
+        debug
+            .field("auth", &self.auth)
+            .field("retry_policy", &self.retry_policy)
+            .field("capture_raw", &self.capture_raw)
+            .field("max_redirects", &self.max_redirects)
+            .field("refuse_over_budget", &self.refuse_over_budget)
+            .field("accept_language", &self.accept_language)
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("throttle", &self.throttle)
+            .field("extra_headers", &RedactedHeaders(&self.extra_headers))
+            .field("coalesce_requests", &self.coalesce_requests)
+            .field("max_pages", &self.max_pages);
+        #[cfg(feature = "cache")]
+        debug.field("cache", &self.cache);
+        #[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+        debug.field("insecure_tls", &self.insecure_tls);
+        debug.finish()
+    }
+}
+
+#[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+#[derive(Debug, Clone, Default)]
+struct InsecureTls {
+    trusted_der_certificates: Vec<Vec<u8>>,
+    disable_hostname_verification: bool,
+}
+
+impl ImgurClientBuilder {
+    /// Sets how requests are authenticated, e.g. with a user's OAuth2 access token
+    /// instead of the client id.
+    ///
+    /// Methods documented as requiring an access token fail with
+    /// `ErrorKind::Unauthorized` before making a request unless this is an
+    /// `Auth::Bearer`.
+    pub fn auth(mut self, auth: Auth) -> ImgurClientBuilder {
+        self.auth = auth;
+        self
+    }
+
+    /// Sets how requests failing with `429 Too Many Requests` or a server error are
+    /// retried. By default they aren't.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> ImgurClientBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Delays requests once few credits are left, spacing them out until the credits
+    /// are reset (see `Throttle`). Off by default. Unlike `retry_policy`, this avoids
+    /// `429 Too Many Requests` instead of recovering from it, which suits long-running
+    /// bulk jobs.
+    pub fn throttle(mut self, throttle: Throttle) -> ImgurClientBuilder {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Keeps the raw JSON body of every API response in `Response::raw`, for debugging
+    /// schema mismatches such as fields the crate doesn't model yet. Off by default, in
+    /// which case nothing is copied.
+    pub fn capture_raw(mut self, capture_raw: bool) -> ImgurClientBuilder {
+        self.capture_raw = capture_raw;
+        self
+    }
+
+    /// Sets how many redirects `download` follows before giving up with
+    /// `ErrorKind::TooManyRedirects`. Defaults to 5; 0 disables following redirects.
+    pub fn max_redirects(mut self, max_redirects: u32) -> ImgurClientBuilder {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Sends `Accept-Language: <languages>` (e.g. `"de-DE, de;q=0.8"`) with every API
+    /// request, which makes imgur localize what it can, such as gallery topics. By
+    /// default no `Accept-Language` header is sent.
+    pub fn accept_language(mut self, languages: String) -> ImgurClientBuilder {
+        self.accept_language = Some(languages);
+        self
+    }
+
+    /// Sets the largest API response body the client reads, failing with
+    /// `ErrorKind::BodyTooLarge` once a body grows beyond it, so that a misbehaving
+    /// proxy can't exhaust memory. Defaults to 64 MiB, far more than any imgur response
+    /// needs. Neither `download` nor `album_images_streaming` are limited, as they
+    /// don't hold on to a JSON body.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> ImgurClientBuilder {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Adds a header to every API request, e.g. `X-Mashape-Key` for access through the
+    /// RapidAPI proxy. Can be called repeatedly to add several headers.
+    ///
+    /// The client's own headers take precedence: an extra `Authorization` header is
+    /// replaced by the one from `auth`, an extra `Accept-Language` by the one from
+    /// `accept_language` (if set), and the content headers of request bodies always
+    /// describe the actual body. Downloads from the CDN don't get extra headers.
+    pub fn header(mut self, name: String, value: String) -> ImgurClientBuilder {
+        self.extra_headers.push((name, value));
+        self
+    }
+
+    /// Makes concurrent `GET` requests for the same URL share one request, e.g. when
+    /// many tasks ask for the same image at once. Everyone waiting gets the same
+    /// response (or a copy of the same error), and the request only costs credits once.
+    /// Off by default. Clones of the client share the requests in flight.
+    ///
+    /// Only requests overlapping in time are shared; use `cache` to keep responses
+    /// around afterwards.
+    pub fn coalesce_requests(mut self, coalesce: bool) -> ImgurClientBuilder {
+        self.coalesce_requests = coalesce;
+        self
+    }
+
+    /// Limits the streams such as `gallery_stream` to `max_pages` pages each (see
+    /// `Paginator::max_pages`). Unlimited by default.
+    ///
+    /// Every page costs a credit, so without a limit a careless `collect` over a huge
+    /// feed can use up the rate limit. A `RetryPolicy` or `Throttle` only spreads
+    /// those requests out; this bounds them.
+    pub fn max_pages(mut self, max_pages: u32) -> ImgurClientBuilder {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Makes batch helpers such as `delete_images` fail with
+    /// `ErrorKind::InsufficientCredits`, without making any requests, if their
+    /// `estimated_cost` exceeds the credits left according to the last response's
+    /// `RateLimitInfo`. Off by default. Jobs always run if no response was seen yet.
+    pub fn refuse_over_budget(mut self, refuse: bool) -> ImgurClientBuilder {
+        self.refuse_over_budget = refuse;
+        self
+    }
+
+    /// Caches successful `image` and `album` responses in memory, keeping up to
+    /// `capacity` of each for `ttl`. Cache hits don't make a request, so they don't use
+    /// any API credits. Clones of the client share the cache. Errors aren't cached.
+    ///
+    /// Only available with the `cache` feature.
+    #[cfg(feature = "cache")]
+    pub fn cache(mut self, capacity: usize, ttl: Duration) -> ImgurClientBuilder {
+        self.cache = Some((capacity, ttl));
+        self
+    }
+
+    /// **DANGER: for testing only.** Trusts the DER-encoded certificate `der` as a root
+    /// certificate, e.g. the self-signed certificate of a local mock server.
+    ///
+    /// Only available with the `dangerous-insecure-tls` feature in debug builds. Any
+    /// server presenting this certificate (or one signed by it) is trusted, so never
+    /// use this against the real API.
+    #[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+    pub fn danger_trust_der_certificate(mut self, der: Vec<u8>) -> ImgurClientBuilder {
+        self.insecure_tls.trusted_der_certificates.push(der);
+        self
+    }
+
+    /// **DANGER: for testing only.** Disables hostname verification, so that a mock
+    /// server can present a certificate issued for a different host (e.g. `localhost`
+    /// serving a certificate for `api.imgur.com`).
+    ///
+    /// Only available with the `dangerous-insecure-tls` feature in debug builds. Without
+    /// hostname verification any valid certificate for any site is trusted for every
+    /// site, which makes man-in-the-middle attacks trivial.
+    #[cfg(all(feature = "dangerous-insecure-tls", debug_assertions))]
+    pub fn danger_disable_hostname_verification(mut self) -> ImgurClientBuilder {
+        self.insecure_tls.disable_hostname_verification = true;
+        self
+    }
+
+    #[cfg(all(
+        feature = "tokio-core",
+        not(all(feature = "dangerous-insecure-tls", debug_assertions))
+    ))]
+    fn connector(&self, handle: &Handle) -> Result<HttpsConnector<HttpConnector>> {
+        Ok(HttpsConnector::new(DEFAULT_THREADS, handle)?)
+    }
+
+    #[cfg(all(
+        feature = "tokio-core",
+        feature = "dangerous-insecure-tls",
+        debug_assertions
+    ))]
+    fn connector(&self, handle: &Handle) -> Result<HttpsConnector<HttpConnector>> {
+        let mut http = HttpConnector::new(DEFAULT_THREADS, handle);
+        http.enforce_http(false);
+        let mut tls = native_tls::TlsConnector::builder()?;
+        for der in &self.insecure_tls.trusted_der_certificates {
+            tls.add_root_certificate(native_tls::Certificate::from_der(der)?)?;
+        }
+        let mut connector = HttpsConnector::from((http, tls.build()?));
+        if self.insecure_tls.disable_hostname_verification {
+            warn!("TLS hostname verification is disabled");
+            connector.danger_disable_hostname_verification(true);
+        }
+        Ok(connector)
+    }
+
+    /// Creates the client, running its connections on `handle`.
+    #[cfg(feature = "tokio-core")]
+    pub fn build(self, handle: &Handle) -> Result<ImgurClient> {
+        let connector = self.connector(handle)?;
+        let client = Client::configure().connector(connector).build(handle);
+        Ok(self.build_with_client(client, handle))
+    }
+
+    /// Creates the client on a background thread with its own event loop (see
+    /// `BackgroundClient`). Fails if the thread or the client can't be created.
+    #[cfg(feature = "tokio-core")]
+    pub fn spawn(self) -> Result<BackgroundClient> {
+        BackgroundClient::spawn(self)
+    }
+
+    /// Creates the client using a supplied `hyper::Client`. `handle` is used for
+    /// timers, e.g. for delaying retries.
+    #[cfg(feature = "tokio-core")]
+    pub fn build_with_client(self, client: HttpsClient, handle: &Handle) -> ImgurClient {
+        self.build_with_timer(client, handle.clone())
+    }
+
+    /// Creates the client using a supplied `hyper::Client`, which can run on any
+    /// executor (see `hyper::client::Config::executor`), and `timer` for delaying
+    /// retries and throttled requests.
+    ///
+    /// ```rust,no_run
+    /// # extern crate hyper;
+    /// # extern crate hyper_tls;
+    /// # extern crate imgur_api;
+    /// # #[cfg(feature = "tokio-core")]
+    /// # extern crate tokio_core;
+    /// # use imgur_api::ImgurClient;
+    /// # #[cfg(not(feature = "tokio-core"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "tokio-core")]
+    /// # fn main() {
+    /// let core = tokio_core::reactor::Core::new().unwrap();
+    /// let connector = hyper_tls::HttpsConnector::new(2, &core.handle()).unwrap();
+    /// let client = hyper::Client::configure()
+    ///     .connector(connector)
+    ///     .executor(core.handle());
+    /// let api = ImgurClient::builder("client id".into()).build_with_timer(client, core.handle());
+    /// # }
+    /// ```
+    pub fn build_with_timer<T>(self, client: HttpsClient, timer: T) -> ImgurClient
+    where
+        T: Timer + 'static,
+    {
+        ImgurClient {
+            client,
+            timer: Rc::new(timer),
+            auth: self.auth,
+            retry_policy: self.retry_policy,
+            capture_raw: self.capture_raw,
+            max_redirects: self.max_redirects,
+            #[cfg(feature = "cache")]
+            cache: self
+                .cache
+                .map(|(capacity, ttl)| Arc::new(cache::ResponseCache::new(capacity, ttl))),
+            requests: Arc::default(),
+            rate_limit: Arc::default(),
+            refuse_over_budget: self.refuse_over_budget,
+            accept_language: self.accept_language,
+            max_body_bytes: self.max_body_bytes,
+            throttle: self.throttle,
+            extra_headers: self.extra_headers,
+            in_flight_gets: if self.coalesce_requests {
+                Some(coalesce::InFlightGets::default())
+            } else {
+                None
+            },
+            max_pages: self.max_pages,
+        }
+    }
+}
+
+/// The headers sent with every API request.
+#[derive(Debug, Clone)]
+struct CommonHeaders {
+    authorization: String,
+    accept_language: Option<String>,
+    /// From `ImgurClientBuilder::header`, applied first so they can't replace the
+    /// others.
+    extra: Vec<(String, String)>,
+}
+
+impl CommonHeaders {
+    fn apply(&self, headers: &mut Headers) {
+        for (name, value) in &self.extra {
+            headers.append_raw(name.clone(), value.clone());
+        }
+        headers.set(Authorization(self.authorization.clone()));
+        if let Some(ref languages) = self.accept_language {
+            headers.set_raw("Accept-Language", languages.clone());
+        }
+    }
+}
+
+/// Waits for a slot from `throttle`, if any, reserving it once the future is polled.
+fn throttled(
+    timer: Rc<dyn Timer>,
+    throttle: Option<Throttle>,
+    rate_limit: Arc<Mutex<Option<RateLimitInfo>>>,
+) -> impl Future<Item = (), Error = Error> {
+    future::lazy(move || {
+        let info = *rate_limit.lock().unwrap();
+        match throttle.and_then(|throttle| throttle.reserve(info)) {
+            Some(wait) => {
+                debug!("Throttling request for {:?}", wait);
+                Either::A(timer.delay(wait))
+            }
+            None => Either::B(future::ok(())),
+        }
+    })
+}
+
+/// An API request with `body`, if any. The body is always sent with a `Content-Length`
+/// rather than chunked, as imgur rejects chunked uploads.
+fn build_request(
+    method: Method,
+    url: Uri,
+    headers: &CommonHeaders,
+    body: Option<&RequestBody>,
+) -> Request {
+    let mut request = Request::new(method, url);
+    headers.apply(request.headers_mut());
+    if let Some(body) = body {
+        request.headers_mut().set(body.content_type.clone());
+        request
+            .headers_mut()
+            .set(ContentLength(body.bytes.len() as u64));
+        request.set_body(body.bytes.clone());
+    }
+    request
+}
+
+/// Collects a response body, failing with `ErrorKind::BodyTooLarge` as soon as it's
+/// longer than `max_body_bytes`.
+fn concat_limited<S>(body: S, max_body_bytes: usize) -> impl Future<Item = Vec<u8>, Error = Error>
+where
+    S: Stream<Error = hyper::Error>,
+    S::Item: AsRef<[u8]>,
+{
+    body.map_err(transport_error)
+        .fold(Vec::new(), move |mut body, chunk| {
+            let chunk = chunk.as_ref();
+            if body.len() + chunk.len() > max_body_bytes {
+                return Err(Error::from(ErrorKind::BodyTooLarge(max_body_bytes)));
+            }
+            body.extend_from_slice(chunk);
+            Ok(body)
+        })
+}
+
+/// The direct link to an image in `size`, see `ImgurClient::download_by_id`.
+fn cdn_url(input: &str, size: Size) -> Result<String> {
+    let id = expect_kind(input, ResourceKind::Image)?;
+    let extension = match size {
+        Size::Original => input
+            .split(&['?', '#'][..])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .and_then(|segment| segment.rfind('.').map(|dot| &segment[dot + 1..]))
+            .filter(|extension| {
+                !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+            .map(|extension| {
+                if extension == "gifv" {
+                    "mp4"
+                } else {
+                    extension
+                }
+            })
+            .unwrap_or("jpg"),
+        _ => "jpg",
+    };
+    Ok(format!(
+        "https://i.imgur.com/{}{}.{}",
+        id,
+        size.suffix(),
+        extension
+    ))
+}
+
+fn vote_url(gallery_id: &str, vote: Vote) -> String {
+    format!(
+        "{}/gallery/{}/vote/{}",
+        API,
+        form::percent_encode(gallery_id),
+        vote
+    )
+}
+
+/// Converts an error from sending a request or reading a response, telling network
+/// failures, TLS handshake failures and timeouts apart. The `hyper::Error` is kept as
+/// the cause.
+fn transport_error(error: hyper::Error) -> Error {
+    let kind = match error {
+        hyper::Error::Timeout => ErrorKind::Timeout,
+        hyper::Error::Io(ref io) if io.kind() == io::ErrorKind::TimedOut => ErrorKind::Timeout,
+        hyper::Error::Io(ref io) if io.get_ref().map_or(false, |e| e.is::<native_tls::Error>()) => {
+            ErrorKind::TlsHandshake
+        }
+        hyper::Error::Io(_) => ErrorKind::Network,
+        _ => return error.into(),
+    };
+    Error::with_chain(error, kind)
+}
+
+/// Where a redirect response points to, resolved against the URL that was requested.
+/// `None` if the response isn't a redirect (or lacks a `Location`).
+fn redirect_target(uri: &Uri, resp: &hyper::Response) -> Option<Result<Uri>> {
+    match resp.status() {
+        StatusCode::MovedPermanently
+        | StatusCode::Found
+        | StatusCode::SeeOther
+        | StatusCode::TemporaryRedirect
+        | StatusCode::PermanentRedirect => {}
+        _ => return None,
+    }
+    let location = resp.headers().get::<Location>()?;
+    Some(resolve_location(uri, location))
+}
+
+/// Resolves the value of a `Location` header, which may be relative, against `base`.
+fn resolve_location(base: &Uri, location: &str) -> Result<Uri> {
+    let scheme = base.scheme().unwrap_or("https");
+    let authority = base.authority().unwrap_or("");
+    let absolute = if location.starts_with("http://") || location.starts_with("https://") {
+        location.to_string()
+    } else if location.starts_with("//") {
+        format!("{}:{}", scheme, location)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+        format!("{}://{}{}{}", scheme, authority, dir, location)
+    };
+    absolute
+        .parse()
+        .map_err(|e| Error::from(hyper::Error::from(e)))
+}
+
+/// Runs `futures` with at most `max_concurrency` of them in flight at once, collecting
+/// their results in the original order.
+fn run_bounded<I>(
+    futures: I,
+    max_concurrency: usize,
+) -> impl Future<Item = Vec<<I::Item as Future>::Item>, Error = Error>
+where
+    I: IntoIterator,
+    I::Item: Future<Error = Error>,
+{
+    stream::iter_ok(futures)
+        .buffered(cmp::max(1, max_concurrency))
+        .collect()
+}
+
+/// A request body along with its content type.
+#[derive(Debug, Clone)]
+struct RequestBody {
+    content_type: ContentType,
+    bytes: Vec<u8>,
+}
+
+impl From<Multipart> for RequestBody {
+    fn from(form: Multipart) -> RequestBody {
+        RequestBody {
+            content_type: ContentType(form.content_type().parse().unwrap()),
+            bytes: form.finish(),
+        }
+    }
+}
+
+impl RequestBody {
+    fn urlencoded(pairs: &[(&str, &str)]) -> RequestBody {
+        RequestBody {
+            content_type: ContentType::form_url_encoded(),
+            bytes: form::urlencoded(pairs).into_bytes(),
+        }
+    }
+}
+
+fn gallery_tags_form(tags: &[&str]) -> RequestBody {
+    RequestBody::urlencoded(&[("tags", &tags.join(","))])
+}
+
+/// The API URL for `path`, for `get_raw` and `post_raw`.
+fn raw_url(path: &str) -> Result<Uri> {
+    format!("{}/{}", API, path.trim_start_matches('/'))
+        .parse()
+        .map_err(|e| Error::from(hyper::Error::from(e)))
+}
+
+fn image_url(id: &str) -> String {
+    format!("{}/image/{}", API, form::percent_encode(id))
+}
+
+fn gallery_url(section: Section, sort: Sort, window: Window, page: u32) -> String {
+    format!(
+        "{}/gallery/{}/{}/{}/{}",
+        API,
+        section.as_str(),
+        sort.as_str(),
+        window.as_str(),
+        page
+    )
+}
+
+fn subreddit_gallery_url(subreddit: &str, sort: Sort, window: Window, page: u32) -> String {
+    format!(
+        "{}/gallery/r/{}/{}/{}/{}",
+        API,
+        form::percent_encode(subreddit.trim_start_matches("/r/")),
+        sort.as_str(),
+        window.as_str(),
+        page
+    )
+}
+
+macro_rules! string_enum {
+    (
+        $(#[$attr:meta])* $name:ident, $what:expr,
+        $($(#[$variant_attr:meta])* $variant:ident => $value:expr,)+
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$variant_attr])* $variant,)+
+        }
+
+        impl $name {
+            fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value,)+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        /// Parses the name imgur uses for the value, ignoring ASCII case.
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(input: &str) -> Result<$name> {
+                $(if input.eq_ignore_ascii_case($value) {
+                    return Ok($name::$variant);
+                })+
+                let valid = [$($value),+].join(", ");
+                Err(ErrorKind::InvalidValue($what.into(), input.into(), valid).into())
+            }
+        }
+    };
+}
+
+string_enum!(
+    /// A section of the gallery, for `ImgurClient::gallery`.
+    Section, "section",
+    /// The most viral posts.
+    Hot => "hot",
+    /// The highest scoring posts within a `Window`.
+    Top => "top",
+    /// User submitted posts.
+    User => "user",
+);
+
+string_enum!(
+    /// How gallery posts are sorted, for `ImgurClient::gallery`.
+    Sort, "sort",
+    /// By virality.
+    Viral => "viral",
+    /// By score.
+    Top => "top",
+    /// Newest first.
+    Time => "time",
+    /// By how quickly they are gaining votes. Only works with `Section::User`.
+    Rising => "rising",
+);
+
+string_enum!(
+    /// The time window for `Section::Top`.
+    Window, "window",
+    /// The last day.
+    Day => "day",
+    /// The last week.
+    Week => "week",
+    /// The last month.
+    Month => "month",
+    /// The last year.
+    Year => "year",
+    /// All time.
+    All => "all",
+);
+
+string_enum!(
+    /// A vote on a gallery post, for `ImgurClient::vote`. Parses the `vote` field of
+    /// gallery posts, too.
+    Vote, "vote",
+    /// An upvote.
+    Up => "up",
+    /// A downvote.
+    Down => "down",
+    /// Removes the user's vote.
+    Veto => "veto",
+);
+
+impl Vote {
+    /// The user's vote after casting this one, given their vote before (e.g. the
+    /// post's `vote` field): voting the same way twice or vetoing clears the vote.
+    pub fn after(self, previous: Option<Vote>) -> Option<Vote> {
+        match self {
+            Vote::Veto => None,
+            vote if previous == Some(vote) => None,
+            vote => Some(vote),
+        }
+    }
+}
+
+string_enum!(
+    /// The order of `ImgurClient::account_favorites`.
+    FavoritesSort, "favorites sort",
+    /// Most recently favorited first.
+    Newest => "newest",
+    /// Least recently favorited first.
+    Oldest => "oldest",
+);
+
+string_enum!(
+    /// Who can see an album.
+    Privacy, "privacy",
+    /// Anyone, and it can show up in searches.
+    Public => "public",
+    /// Anyone with the link.
+    Hidden => "hidden",
+    /// Only the owner.
+    Secret => "secret",
+);
+
+string_enum!(
+    /// How an album is displayed on imgur.
+    Layout, "layout",
+    /// One image after another, with titles and descriptions.
+    Blog => "blog",
+    /// A grid of thumbnails.
+    Grid => "grid",
+    /// Images next to each other, scrolling horizontally.
+    Horizontal => "horizontal",
+    /// Images below each other, without descriptions.
+    Vertical => "vertical",
+);
+
+/// The account settings to change with `ImgurClient::update_account_settings`. Fields
+/// left as `None` aren't changed.
+#[derive(Debug, Clone, Default)]
+pub struct AccountSettingsUpdate {
+    /// The biography shown on the user's profile.
+    pub bio: Option<String>,
+    /// Whether new uploads are public by default.
+    pub public_images: Option<bool>,
+    /// Whether other users can send the user messages.
+    pub messaging_enabled: Option<bool>,
+    /// The default privacy of new albums.
+    pub album_privacy: Option<Privacy>,
+    /// Accepts imgur's gallery terms, which is required before submitting to the
+    /// gallery.
+    pub accepted_gallery_terms: Option<bool>,
+}
+
+impl AccountSettingsUpdate {
+    fn form(&self) -> RequestBody {
+        let flag = |value: bool| if value { "true" } else { "false" };
+        let mut pairs = Vec::new();
+        if let Some(ref bio) = self.bio {
+            pairs.push(("bio", bio.as_str()));
+        }
+        if let Some(public_images) = self.public_images {
+            pairs.push(("public_images", flag(public_images)));
+        }
+        if let Some(messaging_enabled) = self.messaging_enabled {
+            pairs.push(("messaging_enabled", flag(messaging_enabled)));
+        }
+        if let Some(album_privacy) = self.album_privacy {
+            pairs.push(("album_privacy", album_privacy.as_str()));
+        }
+        if let Some(accepted_gallery_terms) = self.accepted_gallery_terms {
+            pairs.push(("accepted_gallery_terms", flag(accepted_gallery_terms)));
+        }
+        RequestBody::urlencoded(&pairs)
+    }
+}
+
+/// Options for `ImgurClient::create_album`.
+#[derive(Debug, Clone, Default)]
+pub struct AlbumOptions {
+    /// The ids of the images to add, for albums created with an access token.
+    pub ids: Vec<String>,
+    /// The deletehashes of the images to add, for anonymous albums.
+    pub deletehashes: Vec<String>,
+    /// The title of the album.
+    pub title: Option<String>,
+    /// The description of the album.
+    pub description: Option<String>,
+    /// Who can see the album. Defaults to the user's `album_privacy` setting.
+    pub privacy: Option<Privacy>,
+    /// The id of the image to show as the album's cover. Defaults to the first image.
+    pub cover: Option<String>,
+}
+
+impl AlbumOptions {
+    fn validate(&self) -> Result<()> {
+        check_length("title", &self.title, MAX_TITLE_LENGTH)?;
+        check_length("description", &self.description, MAX_DESCRIPTION_LENGTH)
+    }
+
+    fn form(&self) -> RequestBody {
+        let mut pairs = Vec::new();
+        for id in &self.ids {
+            pairs.push(("ids[]", id.as_str()));
+        }
+        for hash in &self.deletehashes {
+            pairs.push(("deletehashes[]", hash.as_str()));
+        }
+        if let Some(ref title) = self.title {
+            pairs.push(("title", title.as_str()));
+        }
+        if let Some(ref description) = self.description {
+            pairs.push(("description", description.as_str()));
+        }
+        if let Some(privacy) = self.privacy {
+            pairs.push(("privacy", privacy.as_str()));
+        }
+        if let Some(ref cover) = self.cover {
+            pairs.push(("cover", cover.as_str()));
+        }
+        RequestBody::urlencoded(&pairs)
+    }
+}
+
+/// The response to `ImgurClient::create_album`.
+///
+/// `id` and `deletehash` are the album's own. Deleting with that deletehash deletes the
+/// album, not its images; for the images, use the deletehashes in `images`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumCreateResponse {
+    /// The id of the new album.
+    pub id: String,
+    /// For changing or deleting the album without an access token.
+    pub deletehash: Option<DeleteHash>,
+    /// The images added to the album, with their own deletehashes, if imgur included
+    /// them.
+    #[serde(default)]
+    pub images: Vec<AlbumImageHash>,
+}
+
+/// An image in an `AlbumCreateResponse`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumImageHash {
+    /// The id of the image.
+    pub id: String,
+    /// The image's deletehash, for `ImgurClient::delete_image`.
+    pub deletehash: Option<DeleteHash>,
+}
+
+/// The outcome of `ImgurClient::download_album`.
+#[derive(Debug)]
+pub struct AlbumDownloadReport {
+    /// The images that were downloaded, with their bytes, in album order.
+    pub succeeded: Vec<(Image, Vec<u8>)>,
+    /// The ids of the images that couldn't be downloaded, with the reason, in album
+    /// order.
+    pub failed: Vec<(String, Error)>,
+}
+
+impl AlbumDownloadReport {
+    fn new(results: Vec<(Image, Result<Vec<u8>>)>) -> AlbumDownloadReport {
+        let mut report = AlbumDownloadReport {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+        };
+        for (image, result) in results {
+            match result {
+                Ok(bytes) => report.succeeded.push((image, bytes)),
+                Err(e) => report.failed.push((image.id, e)),
+            }
+        }
+        report
+    }
+
+    /// The number of images that were downloaded.
+    pub fn succeeded_count(&self) -> usize {
+        self.succeeded.len()
+    }
+
+    /// The number of images that couldn't be downloaded.
+    pub fn failed_count(&self) -> usize {
+        self.failed.len()
+    }
+
+    /// The number of bytes downloaded, over all images.
+    pub fn total_bytes(&self) -> u64 {
+        self.succeeded
+            .iter()
+            .map(|(_, bytes)| bytes.len() as u64)
+            .sum()
+    }
+}
+
+/// Options for `ImgurClient::submit_to_gallery`.
+#[derive(Debug, Clone, Default)]
+pub struct GallerySubmitOptions {
+    /// The topic to file the post under, by name or id (see `ImgurClient::topics` and
+    /// `GallerySubmitOptions::in_topic`).
+    pub topic: Option<String>,
+    /// Accepts imgur's gallery terms on the user's behalf. Submissions by users who
+    /// haven't accepted the terms fail otherwise; only set this if your application
+    /// has shown them the terms.
+    pub bypass_terms: bool,
+}
+
+impl GallerySubmitOptions {
+    /// Options filing the post under `topic`, by its id.
+    pub fn in_topic(topic: &Topic) -> GallerySubmitOptions {
+        GallerySubmitOptions {
+            topic: Some(topic.id.to_string()),
+            ..GallerySubmitOptions::default()
+        }
+    }
+
+    fn form(&self, title: &str) -> RequestBody {
+        let mut pairs = vec![("title", title)];
+        if let Some(ref topic) = self.topic {
+            pairs.push(("topic", topic));
+        }
+        if self.bypass_terms {
+            pairs.push(("terms", "1"));
+        }
+        RequestBody::urlencoded(&pairs)
+    }
+}
+
+/// Options for `ImgurClient::upload_image`.
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// The album to add the image to. Anonymous uploads (with just a client id) have
+    /// to pass the album's `deletehash`; uploads authenticated as the album's owner can
+    /// pass the album id instead.
+    pub album: Option<String>,
+    /// The file name sent along with the image. Defaults to `image` with an extension
+    /// matching the detected format.
+    pub name: Option<String>,
+    /// The title of the image.
+    pub title: Option<String>,
+    /// The description of the image.
+    pub description: Option<String>,
+    /// The `Content-Type` of the image part. By default it's detected from the first
+    /// bytes for PNG, JPEG, GIF and WebP images, falling back to
+    /// `application/octet-stream`.
+    pub content_type: Option<String>,
+}
+
+impl UploadOptions {
+    fn validate(&self) -> Result<()> {
+        check_length("title", &self.title, MAX_TITLE_LENGTH)?;
+        check_length("description", &self.description, MAX_DESCRIPTION_LENGTH)
+    }
+
+    /// The multipart form with the file in `field` (`image` or `video`).
+    fn form(&self, field: &str, bytes: &[u8]) -> RequestBody {
+        self.form_with(Multipart::new(), field, bytes)
+    }
+
+    fn form_with(&self, mut form: Multipart, field: &str, bytes: &[u8]) -> RequestBody {
+        let sniffed = sniff_media_type(bytes);
+        let name = match (self.name.as_ref(), sniffed) {
+            (Some(name), _) => name.clone(),
+            (None, Some((_, extension))) => format!("{}.{}", field, extension),
+            (None, None) => field.to_string(),
+        };
+        let content_type = match (self.content_type.as_ref(), sniffed) {
+            (Some(content_type), _) => content_type.as_str(),
+            (None, Some((mime_type, _))) => mime_type,
+            (None, None) => "application/octet-stream",
+        };
+        form.text("type", "file");
+        form.file(field, &name, content_type, bytes);
+        let fields = [
+            ("album", &self.album),
+            ("name", &self.name),
+            ("title", &self.title),
+            ("description", &self.description),
+        ];
+        for &(field, value) in &fields {
+            if let Some(ref value) = *value {
+                form.text(field, value);
+            }
+        }
+        form.into()
+    }
+}
+
+/// Fails with `ErrorKind::InvalidInput` if `value` has more than `max_length` characters.
+fn check_length(field: &str, value: &Option<String>, max_length: usize) -> Result<()> {
+    match *value {
+        Some(ref value) if value.chars().count() > max_length => {
+            Err(ErrorKind::InvalidInput(field.into(), max_length).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Detects the image or video format from its magic bytes, returning its MIME type and
+/// file extension.
+fn sniff_media_type(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else if bytes.len() >= 10 && &bytes[4..10] == b"ftypqt" {
+        Some(("video/quicktime", "mov"))
+    } else if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+        Some(("video/mp4", "mp4"))
+    } else if bytes.starts_with(b"\x1a\x45\xdf\xa3") {
+        Some(("video/webm", "webm"))
+    } else {
+        None
+    }
+}
+
+/// The sizes imgur serves images in, for `ImgurClient::download_by_id`. The
+/// thumbnails keep the aspect ratio, except for the squares, and are never larger than
+/// the original.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// The image as uploaded.
+    Original,
+    /// A 90x90 square.
+    SmallSquare,
+    /// A 160x160 square.
+    BigSquare,
+    /// At most 160x160.
+    SmallThumbnail,
+    /// At most 320x320.
+    MediumThumbnail,
+    /// At most 640x640.
+    LargeThumbnail,
+    /// At most 1024x1024.
+    HugeThumbnail,
+}
+
+impl Size {
+    /// The letter imgur appends to the id for this size.
+    fn suffix(self) -> &'static str {
+        match self {
+            Size::Original => "",
+            Size::SmallSquare => "s",
+            Size::BigSquare => "b",
+            Size::SmallThumbnail => "t",
+            Size::MediumThumbnail => "m",
+            Size::LargeThumbnail => "l",
+            Size::HugeThumbnail => "h",
+        }
+    }
+}
+
+/// The shape of an image, from `Image::orientation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Taller than wide.
+    Portrait,
+    /// Wider than tall.
+    Landscape,
+    /// As wide as tall.
+    Square,
+}
+
+/// A way of playing animated media, for `Image::playback_url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackFormat {
+    /// An MP4 video, usually by far the smallest.
+    Mp4,
+    /// A WebM video, which older uploads have too.
+    Webm,
+    /// imgur's `.gifv` page, which plays the MP4 version in a browser.
+    Gifv,
+}
+
+/// An advertisement in place of a regular image, from `Image::advertisement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ad {
+    /// What kind of ad it is.
+    pub ad_type: AdType,
+    /// Where the ad links to, if anywhere.
+    pub url: Option<String>,
+}
+
+/// The kinds of ads, from `Image::ad_type`. imgur doesn't document the codes, so
+/// everything but promoted posts comes back as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdType {
+    /// A sponsored post shown in the gallery (code 1).
+    Promoted,
+    /// Any other code, including 0, which imgur sends for images that aren't ads.
+    Other(u32),
+}
+
+impl AdType {
+    fn from_code(code: u32) -> AdType {
+        match code {
+            1 => AdType::Promoted,
+            code => AdType::Other(code),
+        }
+    }
+}
+
+/// The kind of resource an imgur id or URL refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// A single image (`https://imgur.com/<id>`, `https://i.imgur.com/<id>.jpg`).
+    Image,
+    /// An album (`https://imgur.com/a/<id>`).
+    Album,
+    /// A gallery post (`https://imgur.com/gallery/<id>`), which can be either an
+    /// image or an album.
+    Gallery,
+}
+
+/// An imgur id together with the kind of resource it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceId {
+    /// What kind of resource the id refers to.
+    pub kind: ResourceKind,
+    /// The bare id.
+    pub id: String,
+}
+
+impl FromStr for ResourceId {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<ResourceId> {
+        let invalid = || Error::from(ErrorKind::InvalidId(input.to_string()));
+        let trimmed = input.trim();
+        let without_scheme = trimmed
+            .trim_start_matches("https://")
+            .trim_start_matches("http://");
+        let without_query = without_scheme.split(&['?', '#'][..]).next().unwrap_or("");
+
+        let (kind, id) = if without_query.contains('/') {
+            let mut segments = without_query.split('/').filter(|s| !s.is_empty());
+            let host = segments.next().ok_or_else(invalid)?;
+            if !is_imgur_host(host) {
+                return Err(invalid());
+            }
+            let path: Vec<&str> = segments.collect();
+            match path.as_slice() {
+                ["a", id] => (ResourceKind::Album, strip_slug(id)),
+                ["gallery", id] => (ResourceKind::Gallery, strip_slug(id)),
+                [id] => (ResourceKind::Image, strip_extension(id)),
+                _ => return Err(invalid()),
+            }
+        } else if is_imgur_host(without_query) {
+            // `imgur.com` or `i.imgur.com` without a path, not an id with an extension.
+            return Err(invalid());
+        } else {
+            (ResourceKind::Image, strip_extension(without_query))
+        };
+
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+
+        Ok(ResourceId {
+            kind,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Drops the title imgur puts in front of the id in newer album and gallery links, as
+/// in `/a/funny-cats-cXz3nAb`.
+fn strip_slug(segment: &str) -> &str {
+    segment.rsplit('-').next().unwrap_or("")
+}
+
+/// Drops a file extension like `.jpg`. Anything else with a dot is left alone, so
+/// that the id check rejects it.
+fn strip_extension(segment: &str) -> &str {
+    match segment.rfind('.') {
+        Some(dot) if is_extension(&segment[dot + 1..]) => &segment[..dot],
+        _ => segment,
+    }
+}
+
+fn is_extension(extension: &str) -> bool {
+    !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_imgur_host(host: &str) -> bool {
+    host == "imgur.com" || host.ends_with(".imgur.com")
+}
+
+/// Extracts the id and the kind of resource from a bare id or one of the common imgur
+/// URL shapes:
+///
+/// * `abc123` or `abc123.jpg` (image)
+/// * `https://imgur.com/abc123`, `https://i.imgur.com/abc123.jpg` (image)
+/// * `https://imgur.com/a/abc123` (album)
+/// * `https://imgur.com/gallery/abc123` (gallery post)
+///
+/// Album and gallery links with a title in front of the id, like
+/// `https://imgur.com/a/funny-cats-abc1234`, work too. Ids can be 5 or 7 characters
+/// long, depending on their age. Query strings and fragments are ignored. Bare ids
+/// are assumed to be images.
+pub fn parse_id(input: &str) -> Result<(ResourceKind, String)> {
+    let ResourceId { kind, id } = input.parse()?;
+    Ok((kind, id))
+}
+
+macro_rules! id_type {
+    ($(#[$attr:meta])* $name:ident, $as_trait:ident, $as_fn:ident, $what:expr) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        pub struct $name(String);
+
+        impl $name {
+            /// Returns the id as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl<'a> From<&'a str> for $name {
+            fn from(id: &'a str) -> $name {
+                $name(id.to_string())
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> $name {
+                $name(id)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        #[doc = "Types that can be passed to methods expecting "]
+        #[doc = $what]
+        #[doc = " id: the typed id itself, or a plain `str`/`String` for convenience."]
+        pub trait $as_trait {
+            #[doc = "Returns the "]
+            #[doc = $what]
+            #[doc = " id as a string slice."]
+            fn $as_fn(&self) -> &str;
+        }
+
+        impl $as_trait for $name {
+            fn $as_fn(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl $as_trait for str {
+            fn $as_fn(&self) -> &str {
+                self
+            }
+        }
+
+        impl $as_trait for String {
+            fn $as_fn(&self) -> &str {
+                self
+            }
+        }
+    };
+}
+
+id_type!(
+    /// The id of an image. Methods taking an image id don't accept an `AlbumId` (and
+    /// vice versa), which catches mixed-up ids at compile time.
+    ImageId,
+    AsImageId,
+    as_image_id,
+    "an image"
+);
+
+id_type!(
+    /// The id of an album. Methods taking an album id don't accept an `ImageId` (and
+    /// vice versa), which catches mixed-up ids at compile time.
+    AlbumId,
+    AsAlbumId,
+    as_album_id,
+    "an album"
+);
+
+/// Parses `input` and makes sure it isn't obviously the wrong kind of resource.
+/// Gallery ids are accepted for both images and albums, since a gallery post can be
+/// either.
+fn expect_kind(input: &str, expected: ResourceKind) -> Result<String> {
+    let (kind, id) = parse_id(input)?;
+    match kind {
+        ResourceKind::Gallery => Ok(id),
+        k if k == expected => Ok(id),
+        k => Err(ErrorKind::WrongResourceKind(expected, k).into()),
+    }
+}
+
+/// Wrapper type returned from all the web API methods.
+///
+/// Unknown fields, both next to `status`/`success`/`data` and inside the data types,
+/// are ignored rather than rejected: imgur adds fields over time and some endpoints
+/// return extra ones, so none of the types use `#[serde(deny_unknown_fields)]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response<T> {
+    /// HTTP status of the response. Some error responses send it as a string, which is
+    /// accepted too.
+    #[serde(deserialize_with = "lenient::number_or_string")]
+    pub status: usize,
+    /// Whether the response succeeded.
+    pub success: bool,
+    /// Either data returned from the API or an error (see `ResponseData`)
+    pub data: ResponseData<T>,
+    /// The raw JSON body, if the client was built with `capture_raw` enabled.
+    #[serde(skip)]
+    pub raw: Option<String>,
+    /// The rate limits imgur reported along with the response.
+    #[serde(skip)]
+    pub rate_limit: Option<RateLimitInfo>,
+    /// How long imgur says it spent processing the request, from the `Server-Timing`
+    /// header, if it sent one. The rest of the request's latency is spent on the
+    /// network and in the client.
+    #[serde(skip)]
+    pub server_timing: Option<Duration>,
+}
+
+/// Whether the response says it's an HTML page.
+fn is_html(headers: &Headers) -> bool {
+    headers.get::<ContentType>().map_or(false, |content_type| {
+        content_type.to_string().starts_with("text/html")
+    })
+}
+
+/// Fails with `ErrorKind::ServiceUnavailable` if the body is an HTML page rather than
+/// JSON, going by its content type or its first character.
+fn reject_html(status: u16, content_type_html: bool, body: &[u8]) -> Result<()> {
+    let first = body.iter().find(|b| !b.is_ascii_whitespace());
+    if !content_type_html && first != Some(&b'<') {
+        return Ok(());
+    }
+    let text = String::from_utf8_lossy(body);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let snippet: String = words.join(" ").chars().take(200).collect();
+    Err(ErrorKind::ServiceUnavailable(status, snippet).into())
+}
+
+/// Reads the server's processing time from a `Server-Timing` header such as
+/// `cache;dur=0.2, app;dur=41.5`: the metric named `total` if there is one, otherwise
+/// the sum of all durations. `None` if there's no header or it has no durations.
+fn server_timing(headers: &Headers) -> Option<Duration> {
+    let raw = headers.get_raw("Server-Timing")?;
+    let mut total = None;
+    let mut sum = None;
+    for line in raw.iter() {
+        let line = String::from_utf8_lossy(line);
+        for metric in line.split(',') {
+            let mut params = metric.split(';').map(str::trim);
+            let name = params.next().unwrap_or("");
+            let duration = params
+                .filter(|param| param.starts_with("dur="))
+                .map(|param| &param["dur=".len()..])
+                .filter_map(|millis| millis.trim_matches('"').parse::<f64>().ok())
+                .find(|millis| *millis >= 0.0 && millis.is_finite());
+            if let Some(millis) = duration {
+                if name.eq_ignore_ascii_case("total") {
+                    total = Some(millis);
+                }
+                sum = Some(sum.unwrap_or(0.0) + millis);
+            }
+        }
+    }
+    total
+        .or(sum)
+        .map(|millis| Duration::from_micros((millis * 1000.0) as u64))
+}
+
+/// The body of an API response, before it's parsed.
+#[derive(Debug, Clone)]
+struct RawBody {
+    body: Vec<u8>,
+    rate_limit: Option<RateLimitInfo>,
+    server_timing: Option<Duration>,
+}
+
+impl RawBody {
+    fn parse<T>(&self, capture_raw: bool) -> Result<Response<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut response = parse_response(&self.body, capture_raw)?;
+        response.rate_limit = self.rate_limit;
+        response.server_timing = self.server_timing;
+        Ok(response)
+    }
+}
+
+fn parse_response<T>(body: &[u8], capture_raw: bool) -> Result<Response<T>>
+where
+    T: DeserializeOwned,
+{
+    let mut response: Response<T> = serde_json::from_slice(body)?;
+    if capture_raw {
+        response.raw = Some(String::from_utf8_lossy(body).into_owned());
+    }
+    Ok(response)
+}
+
+/// Interprets the response to `validate`'s request. The data is kept as a plain JSON
+/// value, so an error shows up as `ResponseData::Success` and is converted here.
+fn credentials_accepted(response: Response<serde_json::Value>) -> Result<bool> {
+    match (response.success, response.status, response.data) {
+        (true, _, _) => Ok(true),
+        (false, 401, _) | (false, 403, _) => Ok(false),
+        (false, _, ResponseData::Success(data)) => {
+            Err(serde_json::from_value::<ApiError>(data)?.into())
+        }
+        (false, _, ResponseData::Error(e)) => Err(e.into()),
+    }
+}
+
+impl<T> Response<T> {
+    /// Converts the data of a successful response, keeping everything else.
+    fn map_data<U, F>(self, f: F) -> Response<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Response {
+            status: self.status,
+            success: self.success,
+            data: match self.data {
+                ResponseData::Success(data) => ResponseData::Success(f(data)),
+                ResponseData::Error(e) => ResponseData::Error(e),
+            },
+            raw: self.raw,
+            rate_limit: self.rate_limit,
+            server_timing: self.server_timing,
+        }
+    }
+
+    /// Borrows the data if the request succeeded.
+    ///
+    /// ```
+    /// # use imgur_api::{Response, ResponseData};
+    /// let resp = Response {
+    ///     status: 200,
+    ///     success: true,
+    ///     data: ResponseData::Success(42),
+    ///     raw: None,
+    ///     rate_limit: None,
+    ///     server_timing: None,
+    /// };
+    /// assert_eq!(resp.ok(), Some(&42));
+    /// assert!(resp.err().is_none());
+    /// ```
+    pub fn ok(&self) -> Option<&T> {
+        match self.data {
+            ResponseData::Success(ref v) => Some(v),
+            ResponseData::Error(_) => None,
+        }
+    }
+
+    /// Borrows the error if the request failed.
+    ///
+    /// ```
+    /// # use imgur_api::{ApiError, Response, ResponseData};
+    /// let error = ApiError {
+    ///     error: "Unable to find an image with the id, abc".into(),
+    ///     request: "/3/image/abc".into(),
+    ///     method: "GET".into(),
+    /// };
+    /// let resp: Response<()> = Response {
+    ///     status: 404,
+    ///     success: false,
+    ///     data: ResponseData::Error(error),
+    ///     raw: None,
+    ///     rate_limit: None,
+    ///     server_timing: None,
+    /// };
+    /// assert_eq!(resp.err().unwrap().method, "GET");
+    /// assert!(resp.ok().is_none());
+    /// ```
+    pub fn err(&self) -> Option<&ApiError> {
+        match self.data {
+            ResponseData::Success(_) => None,
+            ResponseData::Error(ref e) => Some(e),
+        }
+    }
+}
+
+/// Extension methods for futures resolving to a `Response`.
+pub trait ResponseFutureExt<T>: Future<Item = Response<T>, Error = Error> + Sized {
+    /// Resolves to the response's data instead of the whole `Response`, failing with
+    /// `ErrorKind::Imgur` if the API returned an error.
+    ///
+    /// ```no_run
+    /// # extern crate imgur_api;
+    /// # #[cfg(feature = "tokio-core")]
+    /// # extern crate tokio_core;
+    /// # use imgur_api::{ImgurClient, ResponseFutureExt};
+    /// # #[cfg(not(feature = "tokio-core"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "tokio-core")]
+    /// # fn main() {
+    /// let mut core = tokio_core::reactor::Core::new().unwrap();
+    /// let client = ImgurClient::new(&core.handle(), "client id".into()).unwrap();
+    /// let image = core.run(client.image("PE2NI").into_data()).unwrap();
+    /// println!("{}", image.link);
+    /// # }
+    /// ```
+    fn into_data(self) -> IntoData<Self, T> {
+        self.and_then(response_data)
+    }
+}
+
+/// The future returned by `ResponseFutureExt::into_data`.
+pub type IntoData<F, T> = AndThen<F, Result<T>, fn(Response<T>) -> Result<T>>;
+
+impl<T, F> ResponseFutureExt<T> for F where F: Future<Item = Response<T>, Error = Error> {}
+
+fn response_data<T>(response: Response<T>) -> Result<T> {
+    response.data.into_result()
+}
+
+/// The `data` field of the JSON response can either be some data (e.g. data for an image)
+/// or an error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseData<T> {
+    /// Successful response.
+    Success(T),
+    /// Error response.
+    Error(ApiError),
+}
+
+impl<T> ResponseData<T> {
+    /// Converts `self` into a `Result`.
+    pub fn into_result(self) -> Result<T> {
+        match self {
+            ResponseData::Success(v) => Ok(v),
+            ResponseData::Error(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Error data returned by the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiError {
+    /// Error description.
+    pub error: String,
+    /// Request URL.
+    pub request: String,
+    /// HTTP method used for the request.
+    pub method: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Request {} {} failed: {}",
+            self.method, self.request, self.error
+        )
+    }
+}
+
+impl error::Error for ApiError {
+    fn description(&self) -> &str {
+        &self.error
+    }
+}
+
+/// The secret that allows deleting or updating an upload without logging in. Its
+/// `Debug` output is redacted, so debug-printing an `Image` or `Album` doesn't leak it;
+/// use `as_str` to get at the value.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DeleteHash(String);
+
+impl DeleteHash {
+    /// The deletehash itself, e.g. to pass to `ImgurClient::delete_image`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for DeleteHash {
+    fn from(hash: String) -> DeleteHash {
+        DeleteHash(hash)
+    }
+}
+
+impl fmt::Debug for DeleteHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DeleteHash(***)")
+    }
+}
+
+/// Data returned for an image.
+///
+/// Images compare equal (and hash the same) if their ids are equal, regardless of the
+/// other fields, so that the same image fetched from different endpoints can be
+/// deduplicated in a `HashSet`.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Image {
+    pub account_id: Option<String>,
+    #[serde(alias = "url")]
+    pub account_url: Option<String>,
+    pub ad_type: u32,
+    pub ad_url: String,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub animated: bool,
+    pub bandwidth: u32,
+    pub datetime: u32,
+    pub description: Option<String>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub favorite: bool,
+    /// Missing for some kinds of media. See `dimensions`.
+    pub height: Option<u32>,
+    pub id: String,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub in_gallery: bool,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub in_most_viral: bool,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub is_ad: bool,
+    pub link: String,
+    #[serde(default, deserialize_with = "lenient::option_bool_or_int")]
+    pub nsfw: Option<bool>,
+    pub section: Option<String>,
+    pub size: u32,
+    pub tags: Vec<String>,
+    pub title: Option<String>,
+    pub views: u32,
+    pub vote: Option<String>,
+    /// Missing for some kinds of media. See `dimensions`.
+    pub width: Option<u32>,
+    /// For videos and animated images, the link to an MP4 version.
+    pub mp4: Option<String>,
+    /// For videos and animated images, the link to imgur's `.gifv` page.
+    pub gifv: Option<String>,
+    /// For some older animated images, the link to a WebM version.
+    pub webm: Option<String>,
+    /// The size of the MP4 version in bytes.
+    pub mp4_size: Option<u64>,
+    /// Only present for images uploaded by the current user (or anonymously, in the
+    /// upload response). Needed to delete or update the image anonymously.
+    pub deletehash: Option<DeleteHash>,
+    /// The image's position in its album, counting from 0, so that
+    /// `Album::sort_images_by_order` can restore the album's order. imgur doesn't send
+    /// it, so it's never serialized; it's filled in from the order of the images in
+    /// `Album::images`, `GalleryAlbum::images`, `album_images` and
+    /// `album_images_streaming`, and `None` for images from anywhere else.
+    #[serde(default, skip_serializing)]
+    pub position: Option<u32>,
+}
+
+impl Image {
+    /// The width and height, or `None` if imgur doesn't know them: it leaves them out
+    /// for some kinds of media and reports 0 for others.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => Some((width, height)),
+            _ => None,
+        }
+    }
+
+    /// The width divided by the height, if the dimensions are known.
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        self.dimensions()
+            .map(|(width, height)| f64::from(width) / f64::from(height))
+    }
+
+    /// Whether the image is taller than wide, wider than tall, or square, if the
+    /// dimensions are known.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.dimensions()
+            .map(|(width, height)| match width.cmp(&height) {
+                cmp::Ordering::Less => Orientation::Portrait,
+                cmp::Ordering::Greater => Orientation::Landscape,
+                cmp::Ordering::Equal => Orientation::Square,
+            })
+    }
+
+    /// The URL for playing an animated image or video, in the `prefer`red format if
+    /// imgur offers it. Otherwise this falls back to the other formats, smallest first
+    /// (MP4, WebM, then `.gifv`), and finally to `link` itself for animated images
+    /// without any video version. `None` for still images.
+    pub fn playback_url(&self, prefer: PlaybackFormat) -> Option<&str> {
+        let url = |format| match format {
+            PlaybackFormat::Mp4 => self.mp4.as_ref().map(String::as_str),
+            PlaybackFormat::Webm => self.webm.as_ref().map(String::as_str),
+            PlaybackFormat::Gifv => self.gifv.as_ref().map(String::as_str),
+        };
+        let fallbacks = [
+            PlaybackFormat::Mp4,
+            PlaybackFormat::Webm,
+            PlaybackFormat::Gifv,
+        ];
+        url(prefer)
+            .or_else(|| fallbacks.iter().filter_map(|&format| url(format)).next())
+            .or_else(|| Some(self.link.as_str()).filter(|_| self.animated))
+    }
+
+    /// The file extension of `link`, without the dot and ignoring any query string,
+    /// e.g. for a "save as" dialog. A `.gifv` link is a page playing the MP4 version
+    /// rather than a file, so it gives `mp4`.
+    pub fn extension(&self) -> Option<&str> {
+        let path = self.link.split(&['?', '#'][..]).next().unwrap_or("");
+        let file = path.rsplit('/').next().unwrap_or("");
+        match file.rfind('.').map(|dot| &file[dot + 1..]) {
+            Some("gifv") => Some("mp4"),
+            Some("") => None,
+            extension => extension,
+        }
+    }
+
+    /// The advertisement data, if the image is an ad (`is_ad`). Clients that don't
+    /// show ads can skip images for which this is `Some`.
+    pub fn advertisement(&self) -> Option<Ad> {
+        if !self.is_ad {
+            return None;
+        }
+        Some(Ad {
+            ad_type: AdType::from_code(self.ad_type),
+            url: Some(self.ad_url.clone()).filter(|url| !url.is_empty()),
+        })
+    }
+
+    /// The subreddit (or other section) the image was posted in, if any. imgur sends an
+    /// empty string rather than `null` for some images, which this also treats as `None`.
+    /// Subreddit names can be passed to `ImgurClient::subreddit_gallery`.
+    pub fn section_name(&self) -> Option<&str> {
+        self.section
+            .as_ref()
+            .map(String::as_str)
+            .filter(|section| !section.is_empty())
+    }
+
+    /// Whether the image is marked as NSFW. imgur leaves `nsfw` out (`None`) when it
+    /// doesn't know, which this treats as *not* NSFW; use `nsfw_or` to pick a different
+    /// default.
+    pub fn is_nsfw(&self) -> bool {
+        self.nsfw_or(false)
+    }
+
+    /// Whether the image is marked as NSFW, returning `unknown` if imgur didn't say.
+    pub fn nsfw_or(&self, unknown: bool) -> bool {
+        self.nsfw.unwrap_or(unknown)
+    }
+
+    /// Whether the image was uploaded by `username`, ignoring case (see
+    /// `normalize_username`), e.g. to check before an owner-only change. `false` for
+    /// anonymous uploads.
+    pub fn is_owned_by(&self, username: &str) -> bool {
+        self.account_url.as_ref().map_or(false, |owner| {
+            normalize_username(owner) == normalize_username(username)
+        })
+    }
+
+    /// Fetches the image's gallery post (see `ImgurClient::gallery_image`), e.g. for
+    /// its favorite count. Resolves to `None` without making a request if the image
+    /// isn't in the gallery.
+    pub fn gallery_info(
+        &self,
+        client: &ImgurClient,
+    ) -> impl Future<Item = Option<Response<GalleryImage>>, Error = Error> {
+        if self.in_gallery {
+            Either::A(client.gallery_image(self.id.as_str()).map(Some))
+        } else {
+            Either::B(future::ok(None))
+        }
+    }
+}
+
+/// Data returned for an album. Like images, albums compare equal (and hash the same) if
+/// their ids are equal.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Album {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub datetime: u32,
+    /// The id of the cover image. `None` for albums without images.
+    pub cover: Option<String>,
+    pub cover_width: Option<u32>,
+    pub cover_height: Option<u32>,
+    #[serde(alias = "url")]
+    pub account_url: Option<String>,
+    pub privacy: String,
+    pub layout: String,
+    pub views: u32,
+    pub link: String,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub favorite: bool,
+    #[serde(default, deserialize_with = "lenient::option_bool_or_int")]
+    pub nsfw: Option<bool>,
+    pub section: Option<String>,
+    /// Position of the album on its owner's album page. This is 0 for every album if
+    /// the owner never reordered them. It says nothing about the order of the images
+    /// *within* the album: `images` is already in album order, which
+    /// `sort_images_by_order` restores.
+    pub order: u32,
+    /// Only present for albums created by the current user (or anonymously).
+    pub deletehash: Option<DeleteHash>,
+    pub images_count: u32,
+    /// In the order the owner arranged them, with each image's `position` set.
+    #[serde(default, deserialize_with = "positioned_images")]
+    pub images: Option<Vec<Image>>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub in_gallery: bool,
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Image) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Image {}
+
+impl Hash for Image {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PartialEq for Album {
+    fn eq(&self, other: &Album) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Album {}
+
+impl Hash for Album {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Album {
+    /// The subreddit (or other section) the album was posted in, if any. imgur sends an
+    /// empty string rather than `null` for some albums, which this also treats as `None`.
+    /// Subreddit names can be passed to `ImgurClient::subreddit_gallery`.
+    pub fn section_name(&self) -> Option<&str> {
+        self.section
+            .as_ref()
+            .map(String::as_str)
+            .filter(|section| !section.is_empty())
+    }
+
+    /// Whether the album is marked as NSFW. imgur leaves `nsfw` out (`None`) when it
+    /// doesn't know, which this treats as *not* NSFW; use `nsfw_or` to pick a different
+    /// default.
+    pub fn is_nsfw(&self) -> bool {
+        self.nsfw_or(false)
+    }
+
+    /// Whether the album is marked as NSFW, returning `unknown` if imgur didn't say.
+    pub fn nsfw_or(&self, unknown: bool) -> bool {
+        self.nsfw.unwrap_or(unknown)
+    }
+
+    /// Whether the album was uploaded by `username`, ignoring case (see
+    /// `normalize_username`), e.g. to check before an owner-only change. `false` for
+    /// anonymous uploads.
+    pub fn is_owned_by(&self, username: &str) -> bool {
+        self.account_url.as_ref().map_or(false, |owner| {
+            normalize_username(owner) == normalize_username(username)
+        })
+    }
+
+    /// The combined size in bytes of the album's images, e.g. to know how much a
+    /// download will take.
+    ///
+    /// Returns `None` if `images` isn't loaded, which is different from `Some(0)` for
+    /// an album that's actually empty. `album_images` always returns the images.
+    pub fn total_size(&self) -> Option<u64> {
+        self.images
+            .as_ref()
+            .map(|images| images.iter().map(|image| u64::from(image.size)).sum())
+    }
+
+    /// The sum of the views of the album's images, which differs from the album's own
+    /// `views`: those count visits to the album page, while images are also viewed
+    /// directly or in other albums.
+    ///
+    /// Returns `None` if `images` isn't loaded.
+    pub fn total_image_views(&self) -> Option<u64> {
+        self.images
+            .as_ref()
+            .map(|images| images.iter().map(|image| u64::from(image.views)).sum())
+    }
+
+    /// Puts `images` back in the order the album's owner arranged them, e.g. after
+    /// sorting them by size or collecting them from parallel downloads. Images without
+    /// a `position` go last, keeping their relative order.
+    pub fn sort_images_by_order(&mut self) {
+        if let Some(ref mut images) = self.images {
+            images.sort_by_key(|image| (image.position.is_none(), image.position));
+        }
+    }
+}
+
+/// Deserializes the images of an album, numbering them in the order imgur sent them.
+fn positioned_images<'de, D>(deserializer: D) -> ::std::result::Result<Option<Vec<Image>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut images: Option<Vec<Image>> = Option::deserialize(deserializer)?;
+    if let Some(ref mut images) = images {
+        number_images(images);
+    }
+    Ok(images)
+}
+
+/// Sets the `position` of an album's images, which are in album order.
+fn number_images(images: &mut [Image]) {
+    for (position, image) in images.iter_mut().enumerate() {
+        image.position = Some(position as u32);
+    }
+}
+
+/// An item in the gallery, which is either a single image or an album.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum GalleryItem {
+    /// A gallery post consisting of a single image.
+    Image(GalleryImage),
+    /// A gallery post consisting of an album.
+    Album(GalleryAlbum),
+}
+
+impl GalleryItem {
+    /// The id of the gallery post (the image or album id).
+    pub fn id(&self) -> &str {
+        match *self {
+            GalleryItem::Image(ref image) => &image.id,
+            GalleryItem::Album(ref album) => &album.id,
+        }
+    }
+
+    /// Whether the post is in imgur's most viral gallery.
+    pub fn in_most_viral(&self) -> bool {
+        match *self {
+            GalleryItem::Image(ref image) => image.in_most_viral,
+            GalleryItem::Album(ref album) => album.in_most_viral,
+        }
+    }
+}
+
+/// Drops the posts of a gallery feed that aren't in the most viral gallery, keeping
+/// the order of the rest.
+pub fn retain_most_viral(items: &mut Vec<GalleryItem>) {
+    items.retain(GalleryItem::in_most_viral);
+}
+
+impl<'de> Deserialize<'de> for GalleryItem {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<GalleryItem, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let is_album = match value.get("is_album") {
+            Some(serde_json::Value::Bool(is_album)) => *is_album,
+            Some(serde_json::Value::Number(n)) => n.as_u64() == Some(1),
+            _ => false,
+        };
+        if is_album {
+            serde_json::from_value(value)
+                .map(GalleryItem::Album)
+                .map_err(de::Error::custom)
+        } else {
+            serde_json::from_value(value)
+                .map(GalleryItem::Image)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+/// Data returned for an image in the gallery.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryImage {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub datetime: u32,
+    #[serde(rename = "type")]
+    pub mime_type: String,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub animated: bool,
+    /// Missing for some kinds of media.
+    pub width: Option<u32>,
+    /// Missing for some kinds of media.
+    pub height: Option<u32>,
+    pub size: u32,
+    pub views: u32,
+    pub bandwidth: u64,
+    pub link: String,
+    pub vote: Option<String>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub favorite: bool,
+    #[serde(default, deserialize_with = "lenient::option_bool_or_int")]
+    pub nsfw: Option<bool>,
+    pub section: Option<String>,
+    #[serde(alias = "url")]
+    pub account_url: Option<String>,
+    pub account_id: Option<u64>,
+    pub ups: u32,
+    pub downs: u32,
+    pub points: i64,
+    pub score: i64,
+    pub comment_count: Option<u32>,
+    pub topic: Option<String>,
+    pub topic_id: Option<u64>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub in_most_viral: bool,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub is_album: bool,
+    /// How many users favorited the image. Not included in every listing.
+    pub favorite_count: Option<u32>,
+}
+
+impl GalleryImage {
+    /// Whether the image has been promoted to the "Most Viral" section of the gallery.
+    pub fn is_promoted(&self) -> bool {
+        self.in_most_viral
+    }
+}
+
+/// Data returned for an album in the gallery.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryAlbum {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub datetime: u32,
+    pub cover: Option<String>,
+    pub cover_width: Option<u32>,
+    pub cover_height: Option<u32>,
+    #[serde(alias = "url")]
+    pub account_url: Option<String>,
+    pub account_id: Option<u64>,
+    pub privacy: Option<String>,
+    pub layout: Option<String>,
+    pub views: u32,
+    pub link: String,
+    pub vote: Option<String>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub favorite: bool,
+    #[serde(default, deserialize_with = "lenient::option_bool_or_int")]
+    pub nsfw: Option<bool>,
+    pub section: Option<String>,
+    pub ups: u32,
+    pub downs: u32,
+    pub points: i64,
+    pub score: i64,
+    pub comment_count: Option<u32>,
+    pub topic: Option<String>,
+    pub topic_id: Option<u64>,
+    pub images_count: u32,
+    /// In the order the owner arranged them, with each image's `position` set.
+    #[serde(default, deserialize_with = "positioned_images")]
+    pub images: Option<Vec<Image>>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub in_most_viral: bool,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub is_album: bool,
+}
+
+/// A comment on an image or album, along with its replies.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    pub image_id: String,
+    pub comment: String,
+    pub author: String,
+    pub author_id: u64,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub on_album: bool,
+    pub album_cover: Option<String>,
+    pub ups: u32,
+    pub downs: u32,
+    pub points: i64,
+    pub datetime: u64,
+    pub parent_id: u64,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub deleted: bool,
+    pub vote: Option<String>,
+    #[serde(default)]
+    pub children: Vec<Comment>,
+}
+
+/// Walks a tree of comments depth-first, each reply right after its parent, e.g. for
+/// rendering an indented thread. Yields every comment with its depth, which is 0 for
+/// the comments in `comments` themselves.
+pub fn flatten_comments<'a>(comments: &'a [Comment]) -> FlattenComments<'a> {
+    FlattenComments {
+        stack: vec![(comments.iter(), 0)],
+    }
+}
+
+/// The iterator returned by `flatten_comments`.
+#[derive(Debug, Clone)]
+pub struct FlattenComments<'a> {
+    stack: Vec<(::std::slice::Iter<'a, Comment>, usize)>,
+}
+
+impl<'a> Iterator for FlattenComments<'a> {
+    type Item = (&'a Comment, usize);
+
+    fn next(&mut self) -> Option<(&'a Comment, usize)> {
+        loop {
+            let (comment, depth) = match self.stack.last_mut() {
+                None => return None,
+                Some(&mut (ref mut siblings, depth)) => (siblings.next(), depth),
+            };
+            match comment {
+                Some(comment) => {
+                    self.stack.push((comment.children.iter(), depth + 1));
+                    return Some((comment, depth));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// The featured and trending gallery tags.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagList {
+    pub featured: Option<String>,
+    pub tags: Vec<TagMeta>,
+}
+
+/// A gallery topic, such as "Funny" or "Aww".
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Topic {
+    pub id: u64,
+    pub name: String,
+    pub description: Option<String>,
+    pub css: Option<String>,
+    #[serde(deserialize_with = "lenient::bool_or_int")]
+    pub ephemeral: bool,
+}
+
+/// Metadata about a gallery tag.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagMeta {
+    pub name: String,
+    pub display_name: String,
+    pub followers: u32,
+    pub total_items: u32,
+    #[serde(default, deserialize_with = "lenient::option_bool_or_int")]
+    pub following: Option<bool>,
+    pub background_hash: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Normalizes a username for comparisons and as a key, e.g. in a cache.
+///
+/// imgur treats usernames case-insensitively: `/account/SomeOne` and `/account/someone`
+/// are the same account, and `account_url` in responses keeps the casing the user
+/// signed up with, which needn't match the casing a caller asked for. Compare
+/// `normalize_username(a) == normalize_username(b)` instead of the raw strings.
+pub fn normalize_username(username: &str) -> String {
+    username.to_lowercase()
+}
+
+/// An account's public profile. imgur calls the username `url` here and `account_url`
+/// elsewhere; both are accepted.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: u64,
+    #[serde(alias = "account_url")]
+    pub url: String,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub reputation: f64,
+    pub created: u64,
+}
+
+/// A trophy on an account's profile, from `ImgurClient::account_trophies`.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trophy {
+    pub id: u64,
+    pub name: String,
+    /// `name` without spaces and punctuation, e.g. for CSS classes.
+    pub name_clean: String,
+    pub description: String,
+    /// Extra information about why the trophy was awarded, e.g. a post title.
+    pub data: Option<String>,
+    /// A link to what the trophy was awarded for.
+    #[serde(alias = "data_link")]
+    pub link: Option<String>,
+    pub datetime: u64,
+    /// The URL of the trophy's picture.
+    pub image: String,
+}
+
+/// The part of `/account/<username>/gallery_profile` that `account_trophies` uses.
+#[derive(Debug, Deserialize)]
+struct GalleryProfile {
+    trophies: Vec<Trophy>,
+}
+
+/// A folder of images in an account, e.g. the favorites folder.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: u64,
+    pub name: String,
+    pub privacy: Option<String>,
+    pub link: Option<String>,
+    pub image_count: u32,
+}
+
+/// The tags applied to a gallery post.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagVotes {
+    pub tags: Vec<TagVote>,
+}
+
+/// A tag applied to a gallery post, with the votes it got.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagVote {
+    pub name: String,
+    pub ups: u32,
+    pub downs: u32,
+}
+
+#[cfg(all(test, feature = "tokio-core"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use tokio_core::reactor::Core;
+
+    use super::*;
+
+    const CLIENT_ID: &str = include_str!("client_id.txt");
+
+    #[test]
+    fn get_image() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let id = "PE2NI";
+        let work = api.image(id);
+        let resp = core.run(work).unwrap();
+        assert_eq!(resp.data.into_result().unwrap().id, id);
+    }
+
+    #[test]
+    fn get_album_images() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let id = "cXz3n";
+        let work = api.album_images(id);
+        let resp = core.run(work).unwrap();
+        let images: Vec<Image> = resp.data.into_result().unwrap();
+        assert!(images.len() > 5);
+    }
+
+    #[test]
+    fn get_album_images_streaming() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let work = api.album_images_streaming("cXz3n").collect();
+        let images = core.run(work).unwrap();
+        assert!(images.len() > 5);
+    }
+
+    #[test]
+    fn get_error() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let id = "cXz";
+        let work = api.album_images(id);
+        let resp = core.run(work).unwrap();
+        assert!(resp.data.into_result().is_err());
+    }
+
+    #[test]
+    fn get_album() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let id = "cXz3n";
+        let work = api.album(id);
+        let resp = core.run(work).unwrap();
+        assert_eq!(resp.data.into_result().unwrap().id, "cXz3n");
+    }
+
+    #[test]
+    fn get_download_album() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let report = core.run(api.download_album("cXz3n", 3)).unwrap();
+        assert!(report.succeeded_count() > 5);
+        assert_eq!(report.failed_count(), 0);
+        for (image, bytes) in report.succeeded {
+            assert_eq!(bytes.len() as u32, image.size);
+        }
+    }
+
+    #[test]
+    fn get_kind_of() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        assert_eq!(core.run(api.kind_of("PE2NI")).unwrap(), ResourceKind::Image);
+        assert_eq!(core.run(api.kind_of("cXz3n")).unwrap(), ResourceKind::Album);
+        assert!(core.run(api.kind_of("cXz")).is_err());
+    }
+
+    #[test]
+    fn get_random_gallery() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let first = core.run(api.random_gallery(0)).unwrap();
+        let second = core.run(api.random_gallery(1)).unwrap();
+        let first: Vec<GalleryItem> = first.data.into_result().unwrap();
+        let second: Vec<GalleryItem> = second.data.into_result().unwrap();
+        assert!(!first.is_empty());
+        assert_ne!(
+            first.iter().map(GalleryItem::id).collect::<Vec<_>>(),
+            second.iter().map(GalleryItem::id).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn get_default_gallery_tags() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let resp = core.run(api.default_gallery_tags()).unwrap();
+        let tags = resp.data.into_result().unwrap();
+        assert!(!tags.tags.is_empty());
+    }
+
+    #[test]
+    fn get_topics() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), CLIENT_ID.into()).unwrap();
+        let resp = core.run(api.topics()).unwrap();
+        assert!(!resp.data.into_result().unwrap().is_empty());
+    }
+
+    const GALLERY_JSON: &str = r#"[
+        {
+            "id": "PE2NI", "title": "An image", "description": null, "datetime": 1519741331,
+            "type": "image/jpeg", "animated": false, "width": 640, "height": 480,
+            "size": 51234, "views": 100, "bandwidth": 5123400, "link": "https://i.imgur.com/PE2NI.jpg",
+            "vote": null, "favorite": false, "nsfw": false, "section": "", "account_url": "someone",
+            "account_id": 1234, "ups": 10, "downs": 1, "points": 9, "score": 12,
+            "comment_count": 3, "topic": "No Topic", "topic_id": 29, "in_most_viral": true,
+            "is_album": false, "favorite_count": 4
+        },
+        {
+            "id": "cXz3n", "title": "An album", "description": null, "datetime": 1519741331,
+            "cover": "PE2NI", "cover_width": 640, "cover_height": 480, "account_url": null,
+            "account_id": null, "privacy": "public", "layout": "blog", "views": 200,
+            "link": "https://imgur.com/a/cXz3n", "vote": null, "favorite": false, "nsfw": null,
+            "section": null, "ups": 20, "downs": 2, "points": 18, "score": 25,
+            "comment_count": null, "topic": null, "topic_id": null, "images_count": 7,
+            "in_most_viral": false, "is_album": true
+        }
+    ]"#;
+
+    #[test]
+    fn deserialize_gallery_items() {
+        let items: Vec<GalleryItem> = serde_json::from_str(GALLERY_JSON).unwrap();
+        match items[0] {
+            GalleryItem::Image(ref image) => {
+                assert_eq!(image.mime_type, "image/jpeg");
+                assert_eq!(image.favorite_count, Some(4));
+                assert!(image.is_promoted());
+            }
+            ref other => panic!("expected an image, got {:?}", other),
+        }
+        match items[1] {
+            GalleryItem::Album(ref album) => assert_eq!(album.images_count, 7),
+            ref other => panic!("expected an album, got {:?}", other),
+        }
+    }
+
+    pub(crate) fn image_fixture(id: &str, size: u32) -> Image {
+        let json = format!(
+            r#"{{
+                "id": "{}", "title": null, "description": null, "datetime": 1519741331,
+                "animated": false, "width": 640, "height": 480, "size": {}, "views": 10,
+                "bandwidth": 100, "vote": null, "favorite": false, "nsfw": null,
+                "section": null, "account_url": null, "account_id": null, "is_ad": false,
+                "in_most_viral": false, "tags": [], "ad_type": 0, "ad_url": "",
+                "in_gallery": false, "link": "https://i.imgur.com/{}.jpg"
+            }}"#,
+            id, size, id
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    pub(crate) fn album_fixture(id: &str, order: u32) -> Album {
+        let json = format!(
+            r#"{{
+                "id": "{}", "title": "Album", "description": "", "datetime": 1519741331,
+                "cover": "PE2NI", "cover_width": 640, "cover_height": 480,
+                "account_url": "someone", "privacy": "public", "layout": "blog",
+                "views": 10, "link": "https://imgur.com/a/{}", "favorite": false,
+                "nsfw": null, "section": null, "order": {}, "deletehash": null,
+                "images_count": 0, "images": [], "in_gallery": false
+            }}"#,
+            id, id, order
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn empty_albums_have_no_cover() {
+        let body = br#"{"data": {"id": "cXz3n", "title": "New album", "description": "",
+            "datetime": 1519741331, "cover": null, "cover_width": null, "cover_height": null,
+            "account_url": "someone", "privacy": "hidden", "layout": "blog", "views": 0,
+            "link": "https://imgur.com/a/cXz3n", "favorite": false, "nsfw": null,
+            "section": null, "order": 0, "deletehash": null, "images_count": 0,
+            "images": [], "in_gallery": false}, "success": true, "status": 200}"#;
+        let resp: Response<Album> = parse_response(body, false).unwrap();
+        let album = resp.data.into_result().unwrap();
+        assert_eq!((album.cover, album.cover_width), (None, None));
+        assert_eq!(
+            album_fixture("a", 0).cover.as_ref().map(String::as_str),
+            Some("PE2NI")
+        );
+    }
+
+    #[test]
+    fn ownership() {
+        let mut image = image_fixture("a", 1);
+        assert!(!image.is_owned_by("someone"));
+        image.account_url = Some("SomeOne".into());
+        assert!(image.is_owned_by("someone"));
+        assert!(!image.is_owned_by("someone_else"));
+        assert!(album_fixture("a", 0).is_owned_by("SOMEONE"));
+    }
+
+    #[test]
+    fn usernames_ignore_case() {
+        assert_eq!(normalize_username("SomeOne"), normalize_username("someone"));
+        assert_eq!(normalize_username("me"), "me");
+    }
+
+    #[test]
+    fn flatten_comment_threads() {
+        fn comment(id: u64, children: &str) -> String {
+            format!(
+                r#"{{"id": {}, "image_id": "x", "comment": "hi", "author": "someone",
+                    "author_id": 1, "on_album": false, "album_cover": null, "ups": 1,
+                    "downs": 0, "points": 1, "datetime": 1519741331, "parent_id": 0,
+                    "deleted": false, "vote": null, "children": [{}]}}"#,
+                id, children
+            )
+        }
+        let leaf = comment(3, "");
+        let thread = format!(
+            "[{}, {}]",
+            comment(1, &format!("{}, {}", comment(2, &leaf), comment(4, ""))),
+            comment(5, "")
+        );
+        let comments: Vec<Comment> = serde_json::from_str(&thread).unwrap();
+        let flat: Vec<(u64, usize)> = flatten_comments(&comments)
+            .map(|(comment, depth)| (comment.id, depth))
+            .collect();
+        assert_eq!(flat, [(1, 0), (2, 1), (3, 2), (4, 1), (5, 0)]);
+        assert_eq!(flatten_comments(&[]).count(), 0);
+    }
+
+    #[test]
+    fn deserialize_folders() {
+        let body = br#"{"data": [
+            {"id": 17, "name": "Favorites", "privacy": "secret", "link": null,
+                "image_count": 3, "is_default": true},
+            {"id": 18, "name": "Cats", "privacy": "public",
+                "link": "https://imgur.com/user/someone/folder/18", "image_count": 0}
+        ], "success": true, "status": 200}"#;
+        let resp: Response<Vec<Folder>> = parse_response(body, false).unwrap();
+        let folders = resp.data.into_result().unwrap();
+        assert_eq!(folders[0].name, "Favorites");
+        assert_eq!(folders[0].image_count, 3);
+        assert!(folders[1].link.is_some());
+    }
+
+    #[test]
+    fn uploader_field_names() {
+        let json = GALLERY_JSON.replace(r#""account_url": "someone""#, r#""url": "someone""#);
+        let items: Vec<GalleryItem> = serde_json::from_str(&json).unwrap();
+        match items[0] {
+            GalleryItem::Image(ref image) => {
+                assert_eq!(
+                    image.account_url.as_ref().map(String::as_str),
+                    Some("someone")
+                )
+            }
+            ref other => panic!("expected an image, got {:?}", other),
+        }
+
+        for field in &["url", "account_url"] {
+            let json = format!(
+                r#"{{"id": 1, "{}": "someone", "bio": null, "avatar": null, "reputation": 12.5,
+                    "created": 1378244862, "pro_expiration": false}}"#,
+                field
+            );
+            let account: Account = serde_json::from_str(&json).unwrap();
+            assert_eq!(account.url, "someone");
+        }
+    }
+
+    #[test]
+    fn filter_most_viral() {
+        let mut items: Vec<GalleryItem> = serde_json::from_str(GALLERY_JSON).unwrap();
+        assert!(items[0].in_most_viral() && !items[1].in_most_viral());
+        retain_most_viral(&mut items);
+        let ids: Vec<&str> = items.iter().map(GalleryItem::id).collect();
+        assert_eq!(ids, ["PE2NI"]);
+    }
+
+    #[test]
+    fn booleans_as_ints() {
+        let json = GALLERY_JSON
+            .replace(r#""animated": false"#, r#""animated": 1"#)
+            .replace(r#""in_most_viral": true"#, r#""in_most_viral": 0"#)
+            .replace(r#""is_album": true"#, r#""is_album": 1"#)
+            .replace(r#""nsfw": false"#, r#""nsfw": 0"#);
+        let items: Vec<GalleryItem> = serde_json::from_str(&json).unwrap();
+        match items[0] {
+            GalleryItem::Image(ref image) => {
+                assert!(image.animated);
+                assert!(!image.in_most_viral);
+                assert_eq!(image.nsfw, Some(false));
+            }
+            ref other => panic!("expected an image, got {:?}", other),
+        }
+        match items[1] {
+            GalleryItem::Album(_) => {}
+            ref other => panic!("expected an album, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sort_images() {
+        let body = br#"{"id": "a", "title": "Album", "description": "", "datetime": 0,
+            "cover": null, "cover_width": null, "cover_height": null, "account_url": null,
+            "privacy": "public", "layout": "blog", "views": 0, "link": "", "favorite": false,
+            "nsfw": null, "section": null, "order": 0, "deletehash": null,
+            "images_count": 0, "in_gallery": false}"#;
+        let album: Album = serde_json::from_slice(body).unwrap();
+        assert!(album.images.is_none());
+
+        let mut album = album_fixture("a", 0);
+        album.images = Some(vec![
+            image_fixture("c", 1),
+            image_fixture("a", 3),
+            image_fixture("d", 2),
+            image_fixture("b", 4),
+        ]);
+        let json = serde_json::to_value(&album).unwrap();
+        assert!(json["images"][0].get("position").is_none());
+        album = serde_json::from_value(json).unwrap();
+        let mut shuffled = album.images.clone().unwrap();
+        shuffled.sort_by_key(|image| image.size);
+        shuffled.push(image_fixture("e", 0));
+        album.images = Some(shuffled);
+
+        album.sort_images_by_order();
+        let ids: Vec<&str> = album
+            .images
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(ids, ["c", "a", "d", "b", "e"]);
+    }
+
+    #[test]
+    fn capture_raw_body() {
+        let body = br#"{"data": 42, "success": true, "status": 200, "unmodeled": [1, 2]}"#;
+        let plain: Response<u32> = parse_response(body, false).unwrap();
+        assert!(plain.raw.is_none());
+        let captured: Response<u32> = parse_response(body, true).unwrap();
+        assert_eq!(captured.ok(), Some(&42));
+        assert!(captured.raw.unwrap().contains("unmodeled"));
+    }
+
+    #[test]
+    fn upload_form_includes_album() {
+        let options = UploadOptions {
+            album: Some("AbCdEfGh".into()),
+            title: Some("A title".into()),
+            ..UploadOptions::default()
+        };
+        let body = options.form_with(Multipart::with_boundary("XYZ".into()), "image", b"bytes");
+        let body = String::from_utf8(body.bytes).unwrap();
+        assert!(body.contains("name=\"album\"\r\n\r\nAbCdEfGh\r\n"));
+        assert!(body.contains("name=\"title\"\r\n\r\nA title\r\n"));
+        assert!(!body.contains("name=\"description\""));
+    }
+
+    #[test]
+    fn sniff_upload_types() {
+        assert_eq!(
+            sniff_media_type(b"\x89PNG\r\n\x1a\n...."),
+            Some(("image/png", "png"))
+        );
+        assert_eq!(
+            sniff_media_type(b"\xff\xd8\xff\xe0"),
+            Some(("image/jpeg", "jpg"))
+        );
+        assert_eq!(sniff_media_type(b"GIF89a.."), Some(("image/gif", "gif")));
+        assert_eq!(
+            sniff_media_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some(("image/webp", "webp"))
+        );
+        assert_eq!(sniff_media_type(b"RIFF\x00\x00\x00\x00WAVE"), None);
+        assert_eq!(
+            sniff_media_type(b"\x00\x00\x00\x18ftypmp42"),
+            Some(("video/mp4", "mp4"))
+        );
+        assert_eq!(
+            sniff_media_type(b"\x00\x00\x00\x14ftypqt  "),
+            Some(("video/quicktime", "mov"))
+        );
+        assert_eq!(
+            sniff_media_type(b"\x1a\x45\xdf\xa3\x01"),
+            Some(("video/webm", "webm"))
+        );
+        assert_eq!(sniff_media_type(b""), None);
+    }
+
+    #[test]
+    fn upload_form_content_type() {
+        let png = b"\x89PNG\r\n\x1a\nrest";
+        let form = |options: UploadOptions, bytes: &[u8]| {
+            let body = options.form_with(Multipart::with_boundary("XYZ".into()), "image", bytes);
+            String::from_utf8_lossy(&body.bytes).into_owned()
+        };
+
+        let body = form(UploadOptions::default(), png);
+        assert!(body.contains("filename=\"image.png\"\r\nContent-Type: image/png\r\n"));
+
+        let body = form(UploadOptions::default(), b"???");
+        assert!(body.contains("filename=\"image\"\r\nContent-Type: application/octet-stream\r\n"));
+
+        let options = UploadOptions {
+            name: Some("cat.apng".into()),
+            content_type: Some("image/apng".into()),
+            ..UploadOptions::default()
+        };
+        let body = form(options, png);
+        assert!(body.contains("filename=\"cat.apng\"\r\nContent-Type: image/apng\r\n"));
+
+        let mp4 = b"\x00\x00\x00\x18ftypmp42rest";
+        let body = UploadOptions::default().form_with(
+            Multipart::with_boundary("XYZ".into()),
+            "video",
+            mp4,
+        );
+        let body = String::from_utf8_lossy(&body.bytes);
+        assert!(
+            body.contains("name=\"video\"; filename=\"video.mp4\"\r\nContent-Type: video/mp4\r\n")
+        );
+    }
+
+    #[test]
+    fn image_shapes() {
+        let mut image = image_fixture("a", 1);
+        assert_eq!(image.aspect_ratio(), Some(640.0 / 480.0));
+        assert_eq!(image.orientation(), Some(Orientation::Landscape));
+
+        image.width = Some(480);
+        image.height = Some(640);
+        assert_eq!(image.orientation(), Some(Orientation::Portrait));
+        image.width = Some(640);
+        assert_eq!(
+            (image.aspect_ratio(), image.orientation()),
+            (Some(1.0), Some(Orientation::Square))
+        );
+
+        image.height = Some(0);
+        assert_eq!(image.dimensions(), None);
+        assert_eq!(image.aspect_ratio(), None);
+        assert_eq!(image.orientation(), None);
+
+        let json = r#"{
+            "id": "a", "title": null, "description": null, "datetime": 1519741331,
+            "animated": true, "size": 1048576, "views": 10, "bandwidth": 100,
+            "vote": null, "favorite": false, "nsfw": null, "section": null,
+            "account_url": null, "account_id": null, "is_ad": false,
+            "in_most_viral": false, "tags": [], "ad_type": 0, "ad_url": "",
+            "in_gallery": false, "link": "https://i.imgur.com/a.mp4"
+        }"#;
+        let video: Image = serde_json::from_str(json).unwrap();
+        assert_eq!((video.width, video.dimensions()), (None, None));
+    }
+
+    #[test]
+    fn identity_by_id() {
+        use std::collections::HashSet;
+
+        let mut other = image_fixture("a", 2);
+        other.title = Some("Same image, other endpoint".into());
+        let images: HashSet<Image> = vec![image_fixture("a", 1), other, image_fixture("b", 1)]
+            .into_iter()
+            .collect();
+        assert_eq!(images.len(), 2);
+
+        let albums: HashSet<Album> = vec![album_fixture("c", 0), album_fixture("c", 1)]
+            .into_iter()
+            .collect();
+        assert_eq!(albums.len(), 1);
+    }
+
+    #[test]
+    fn playback_urls() {
+        let mut image = image_fixture("a", 1);
+        assert_eq!(image.playback_url(PlaybackFormat::Mp4), None);
+        image.animated = true;
+        image.link = "https://i.imgur.com/a.gif".into();
+        let gif = Some("https://i.imgur.com/a.gif");
+        assert_eq!(image.playback_url(PlaybackFormat::Mp4), gif);
+
+        image.gifv = Some("https://i.imgur.com/a.gifv".into());
+        for &format in &[
+            PlaybackFormat::Mp4,
+            PlaybackFormat::Webm,
+            PlaybackFormat::Gifv,
+        ] {
+            assert_eq!(
+                image.playback_url(format),
+                Some("https://i.imgur.com/a.gifv")
+            );
+        }
+
+        image.mp4 = Some("https://i.imgur.com/a.mp4".into());
+        image.webm = Some("https://i.imgur.com/a.webm".into());
+        let mp4 = Some("https://i.imgur.com/a.mp4");
+        assert_eq!(image.playback_url(PlaybackFormat::Mp4), mp4);
+        let webm = Some("https://i.imgur.com/a.webm");
+        assert_eq!(image.playback_url(PlaybackFormat::Webm), webm);
+        let gifv = Some("https://i.imgur.com/a.gifv");
+        assert_eq!(image.playback_url(PlaybackFormat::Gifv), gifv);
+
+        image.mp4 = None;
+        assert_eq!(image.playback_url(PlaybackFormat::Mp4), webm);
+    }
+
+    #[test]
+    fn link_extensions() {
+        let mut image = image_fixture("a", 1);
+        assert_eq!(image.extension(), Some("jpg"));
+        image.link = "https://i.imgur.com/a.gifv".into();
+        assert_eq!(image.extension(), Some("mp4"));
+        image.link = "https://i.imgur.com/a.png?maxwidth=640&fidelity=high".into();
+        assert_eq!(image.extension(), Some("png"));
+        image.link = "https://imgur.com/a".into();
+        assert_eq!(image.extension(), None);
+        image.link = "https://i.imgur.com/a.".into();
+        assert_eq!(image.extension(), None);
+    }
+
+    #[test]
+    fn advertisements() {
+        let mut image = image_fixture("a", 1);
+        assert_eq!(image.advertisement(), None);
+
+        image.is_ad = true;
+        image.ad_type = 1;
+        image.ad_url = "https://example.com/".into();
+        let ad = image.advertisement().unwrap();
+        assert_eq!(ad.ad_type, AdType::Promoted);
+        assert_eq!(
+            ad.url.as_ref().map(String::as_str),
+            Some("https://example.com/")
+        );
+
+        image.ad_type = 7;
+        image.ad_url.clear();
+        let ad = image.advertisement().unwrap();
+        assert_eq!((ad.ad_type, ad.url), (AdType::Other(7), None));
+    }
+
+    #[test]
+    fn album_download_reports() {
+        let results = vec![
+            (image_fixture("a", 3), Ok(vec![0; 3])),
+            (image_fixture("b", 4), Err(ErrorKind::Network.into())),
+            (image_fixture("c", 5), Ok(vec![0; 5])),
+        ];
+        let report = AlbumDownloadReport::new(results);
+        assert_eq!((report.succeeded_count(), report.failed_count()), (2, 1));
+        assert_eq!(report.total_bytes(), 8);
+        assert_eq!(report.succeeded[1].0.id, "c");
+        assert_eq!(report.failed[0].0, "b");
+    }
+
+    #[test]
+    fn album_total_size() {
+        let mut album = album_fixture("a", 0);
+        assert_eq!(album.total_size(), Some(0));
+        album.images = None;
+        assert_eq!(album.total_size(), None);
+        album.images = Some(vec![
+            image_fixture("x", 3_000_000_000),
+            image_fixture("y", 2_000_000_000),
+        ]);
+        assert_eq!(album.total_size(), Some(5_000_000_000));
+    }
+
+    #[test]
+    fn album_total_image_views() {
+        let mut album = album_fixture("a", 0);
+        assert_eq!(album.total_image_views(), Some(0));
+        album.images = Some(vec![image_fixture("x", 1), image_fixture("y", 1)]);
+        assert_eq!(album.total_image_views(), Some(20));
+        album.images = None;
+        assert_eq!(album.total_image_views(), None);
+    }
+
+    #[test]
+    fn nsfw_defaults() {
+        let mut album = album_fixture("a", 0);
+        assert!(!album.is_nsfw());
+        assert!(album.nsfw_or(true));
+        album.nsfw = Some(true);
+        assert!(album.is_nsfw());
+        album.nsfw = Some(false);
+        assert!(!album.nsfw_or(true));
+    }
+
+    #[test]
+    fn gallery_tags_are_comma_joined() {
+        let body = gallery_tags_form(&["cats", "funny pics"]);
+        assert_eq!(body.bytes, b"tags=cats%2Cfunny%20pics");
+        assert_eq!(body.content_type, ContentType::form_url_encoded());
+    }
+
+    #[test]
+    fn raw_urls() {
+        for path in &["credits", "/credits"] {
+            let url = raw_url(path).unwrap();
+            assert_eq!(url.as_ref(), "https://api.imgur.com/3/credits");
+        }
+    }
+
+    #[test]
+    fn cdn_urls() {
+        let cases = [
+            ("PE2NI", Size::Original, "https://i.imgur.com/PE2NI.jpg"),
+            ("PE2NI.png", Size::Original, "https://i.imgur.com/PE2NI.png"),
+            (
+                "https://i.imgur.com/PE2NI.gifv?1",
+                Size::Original,
+                "https://i.imgur.com/PE2NI.mp4",
+            ),
+            (
+                "https://imgur.com/PE2NI",
+                Size::Original,
+                "https://i.imgur.com/PE2NI.jpg",
+            ),
+            (
+                "PE2NI.png",
+                Size::MediumThumbnail,
+                "https://i.imgur.com/PE2NIm.jpg",
+            ),
+            ("PE2NI", Size::SmallSquare, "https://i.imgur.com/PE2NIs.jpg"),
+        ];
+        for &(input, size, url) in &cases {
+            assert_eq!(cdn_url(input, size).unwrap(), url, "{}", input);
+        }
+        assert!(cdn_url("https://imgur.com/a/cXz3n", Size::Original).is_err());
+    }
+
+    #[test]
+    fn votes() {
+        assert_eq!(
+            vote_url("a b", Vote::Up),
+            "https://api.imgur.com/3/gallery/a%20b/vote/up"
+        );
+        assert!(vote_url("x", Vote::Down).ends_with("/gallery/x/vote/down"));
+        assert!(vote_url("x", Vote::Veto).ends_with("/gallery/x/vote/veto"));
+
+        assert_eq!(Vote::Up.after(None), Some(Vote::Up));
+        assert_eq!(Vote::Up.after(Some(Vote::Down)), Some(Vote::Up));
+        assert_eq!(Vote::Up.after(Some(Vote::Up)), None);
+        assert_eq!(Vote::Veto.after(Some(Vote::Down)), None);
+        assert_eq!("down".parse::<Vote>().unwrap(), Vote::Down);
+    }
+
+    #[test]
+    fn gallery_urls() {
+        let cases = [
+            (Section::Hot, Sort::Viral, Window::Day, 0, "hot/viral/day/0"),
+            (Section::Top, Sort::Top, Window::Week, 1, "top/top/week/1"),
+            (
+                Section::Top,
+                Sort::Time,
+                Window::Month,
+                2,
+                "top/time/month/2",
+            ),
+            (
+                Section::Top,
+                Sort::Viral,
+                Window::Year,
+                3,
+                "top/viral/year/3",
+            ),
+            (
+                Section::User,
+                Sort::Rising,
+                Window::All,
+                4,
+                "user/rising/all/4",
+            ),
+        ];
+        for &(section, sort, window, page, path) in &cases {
+            assert_eq!(
+                gallery_url(section, sort, window, page),
+                format!("https://api.imgur.com/3/gallery/{}", path)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_options() {
+        assert_eq!("top".parse::<Sort>().unwrap(), Sort::Top);
+        assert_eq!("Rising".parse::<Sort>().unwrap(), Sort::Rising);
+        assert_eq!("all".parse::<Window>().unwrap(), Window::All);
+        assert_eq!("USER".parse::<Section>().unwrap(), Section::User);
+        assert_eq!("hidden".parse::<Privacy>().unwrap(), Privacy::Hidden);
+        assert_eq!("grid".parse::<Layout>().unwrap(), Layout::Grid);
+        assert_eq!(Window::Week.to_string(), "week");
+
+        let error = "newest".parse::<Sort>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid sort 'newest', expected one of: viral, top, time, rising"
+        );
+        assert!("".parse::<Layout>().is_err());
+    }
+
+    #[test]
+    fn sections() {
+        let mut image = image_fixture("x", 1);
+        assert_eq!(image.section_name(), None);
+        image.section = Some(String::new());
+        assert_eq!(image.section_name(), None);
+        image.section = Some("aww".into());
+        assert_eq!(image.section_name(), Some("aww"));
+        assert_eq!(
+            subreddit_gallery_url("aww", Sort::Top, Window::Week, 2),
+            "https://api.imgur.com/3/gallery/r/aww/top/week/2"
+        );
+        assert_eq!(
+            subreddit_gallery_url("/r/pics", Sort::Time, Window::Day, 0),
+            "https://api.imgur.com/3/gallery/r/pics/time/day/0"
+        );
+    }
+
+    #[test]
+    fn upload_length_limits() {
+        let options = |title: usize, description: usize| UploadOptions {
+            title: Some("é".repeat(title)),
+            description: Some("x".repeat(description)),
+            ..UploadOptions::default()
+        };
+        assert!(options(MAX_TITLE_LENGTH, MAX_DESCRIPTION_LENGTH)
+            .validate()
+            .is_ok());
+        match *options(MAX_TITLE_LENGTH + 1, 0)
+            .validate()
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::InvalidInput(ref field, max) => {
+                assert_eq!((field.as_str(), max), ("title", MAX_TITLE_LENGTH))
+            }
+            ref other => panic!("expected InvalidInput, got {:?}", other),
+        }
+        match *options(0, MAX_DESCRIPTION_LENGTH + 1)
+            .validate()
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::InvalidInput(ref field, _) => assert_eq!(field, "description"),
+            ref other => panic!("expected InvalidInput, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn account_settings_form() {
+        assert!(AccountSettingsUpdate::default().form().bytes.is_empty());
+        let options = AccountSettingsUpdate {
+            bio: Some("Hi there".into()),
+            messaging_enabled: Some(false),
+            album_privacy: Some(Privacy::Hidden),
+            ..AccountSettingsUpdate::default()
+        };
+        assert_eq!(
+            options.form().bytes,
+            &b"bio=Hi%20there&messaging_enabled=false&album_privacy=hidden"[..]
+        );
+    }
+
+    #[test]
+    fn album_options_form() {
+        let options = AlbumOptions {
+            ids: vec!["PE2NI".into(), "xbOkB".into()],
+            title: Some("Cats".into()),
+            cover: Some("xbOkB".into()),
+            ..AlbumOptions::default()
+        };
+        assert_eq!(
+            options.form().bytes,
+            &b"ids%5B%5D=PE2NI&ids%5B%5D=xbOkB&title=Cats&cover=xbOkB"[..]
+        );
+    }
+
+    #[test]
+    fn deserialize_created_albums() {
+        let body = br#"{"data": {"id": "cXz3n", "deletehash": "Ab1cDeFgHiJkLmN",
+            "images": [{"id": "PE2NI", "deletehash": "oPqRsTuVwXyZ012"}]},
+            "success": true, "status": 200}"#;
+        let resp: Response<AlbumCreateResponse> = parse_response(body, false).unwrap();
+        let album = resp.data.into_result().unwrap();
+        assert_eq!(
+            album.deletehash.as_ref().map(DeleteHash::as_str),
+            Some("Ab1cDeFgHiJkLmN")
+        );
+        assert_eq!(album.images[0].id, "PE2NI");
+        assert_eq!(
+            album.images[0].deletehash.as_ref().map(DeleteHash::as_str),
+            Some("oPqRsTuVwXyZ012")
+        );
+
+        let body = br#"{"data": {"id": "cXz3n", "deletehash": "Ab1cDeFgHiJkLmN"},
+            "success": true, "status": 200}"#;
+        let resp: Response<AlbumCreateResponse> = parse_response(body, false).unwrap();
+        assert!(resp.data.into_result().unwrap().images.is_empty());
+    }
+
+    #[test]
+    fn deserialize_trophies() {
+        let body = br#"{"data": {"total_gallery_comments": 40, "total_gallery_favorites": 4,
+            "total_gallery_submissions": 2, "trophies": [{"id": 1, "name": "1 Year",
+            "name_clean": "1Years", "description": "Be a member of Imgur for one year.",
+            "data": null, "data_link": null, "datetime": 1357344455,
+            "image": "http://s.imgur.com/images/trophies/a84ade.png"}]},
+            "success": true, "status": 200}"#;
+        let resp: Response<GalleryProfile> = parse_response(body, false).unwrap();
+        let trophies = resp.map_data(|profile| profile.trophies);
+        let trophies = trophies.data.into_result().unwrap();
+        assert_eq!(trophies[0].name_clean, "1Years");
+        assert_eq!(trophies[0].link, None);
+    }
+
+    #[test]
+    fn deserialize_topics() {
+        let body = br#"{"data": [
+            {"id": 2, "name": "Funny", "description": "if it makes you laugh", "css": null,
+                "ephemeral": false},
+            {"id": 158, "name": "Current Events", "description": null, "css": "current",
+                "ephemeral": 1}
+        ], "success": true, "status": 200}"#;
+        let resp: Response<Vec<Topic>> = parse_response(body, false).unwrap();
+        let topics = resp.data.into_result().unwrap();
+        assert!(!topics[0].ephemeral && topics[1].ephemeral);
+        let options = GallerySubmitOptions::in_topic(&topics[1]);
+        assert_eq!(options.form("x").bytes, b"title=x&topic=158");
+    }
+
+    #[test]
+    fn gallery_submit_form() {
+        let body = GallerySubmitOptions::default().form("My cat");
+        assert_eq!(body.bytes, b"title=My%20cat");
+        let options = GallerySubmitOptions {
+            topic: Some("Aww".into()),
+            bypass_terms: true,
+        };
+        assert_eq!(options.form("x").bytes, b"title=x&topic=Aww&terms=1");
+    }
+
+    #[test]
+    fn string_statuses() {
+        let body = br#"{"data": {"error": "Too Many Requests", "request": "/3/image/abc",
+            "method": "GET"}, "success": false, "status": "429"}"#;
+        let resp: Response<Image> = parse_response(body, false).unwrap();
+        assert_eq!(resp.status, 429);
+        assert_eq!(resp.err().unwrap().error, "Too Many Requests");
+    }
+
+    #[test]
+    fn into_data_flattens_responses() {
+        let success = Response {
+            status: 200,
+            success: true,
+            data: ResponseData::Success(42),
+            raw: None,
+            rate_limit: None,
+            server_timing: None,
+        };
+        assert_eq!(future::ok(success).into_data().wait().unwrap(), 42);
+
+        let error: Response<u32> = parse_response(
+            br#"{"data": {"error": "Not found", "request": "/3/image/x", "method": "GET"},
+                "success": false, "status": 404}"#,
+            false,
+        )
+        .unwrap();
+        match *future::ok(error).into_data().wait().unwrap_err().kind() {
+            ErrorKind::Imgur(ref e) => assert_eq!(e.error, "Not found"),
+            ref other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accept_language_header() {
+        let core = Core::new().unwrap();
+        let mut headers = Headers::new();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        api.common_headers().apply(&mut headers);
+        assert!(headers.get_raw("Accept-Language").is_none());
+
+        let api = ImgurClient::builder("id".into())
+            .accept_language("de-DE, de;q=0.8".into())
+            .build(&core.handle())
+            .unwrap();
+        api.common_headers().apply(&mut headers);
+        let value = headers.get_raw("Accept-Language").and_then(|raw| raw.one());
+        assert_eq!(value, Some(&b"de-DE, de;q=0.8"[..]));
+        assert_eq!(
+            headers.get::<Authorization<String>>().unwrap().0,
+            "Client-ID id"
+        );
+    }
+
+    #[test]
+    fn request_bodies_have_a_content_length() {
+        let core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        let url: Uri = format!("{}/image", API).parse().unwrap();
+        let body = UploadOptions::default().form("image", b"\x89PNG\r\n\x1a\nrest");
+        let request = build_request(Method::Post, url, &api.common_headers(), Some(&body));
+        let length = request.headers().get::<ContentLength>().unwrap().0;
+        assert_eq!(length, body.bytes.len() as u64);
+        let sent = request.body().concat2().wait().unwrap();
+        assert_eq!(sent.len() as u64, length);
+    }
+
+    #[test]
+    fn server_timings() {
+        let mut headers = Headers::new();
+        assert_eq!(server_timing(&headers), None);
+        headers.set_raw("Server-Timing", "cache;desc=\"hit\"");
+        assert_eq!(server_timing(&headers), None);
+        headers.set_raw("Server-Timing", "cache;dur=0.5, app;desc=\"PHP\";dur=41.5");
+        assert_eq!(server_timing(&headers), Some(Duration::from_millis(42)));
+        headers.set_raw("Server-Timing", "db;dur=10, total;dur=\"25\"");
+        assert_eq!(server_timing(&headers), Some(Duration::from_millis(25)));
+    }
+
+    #[test]
+    fn html_responses() {
+        let page = b"\n<!DOCTYPE html>\n<html><head><title>imgur is over capacity!</title>";
+        match *reject_html(503, false, page).unwrap_err().kind() {
+            ErrorKind::ServiceUnavailable(503, ref snippet) => {
+                assert!(snippet.starts_with("<!DOCTYPE html> <html>"));
+                assert!(snippet.contains("over capacity"));
+            }
+            ref other => panic!("expected ServiceUnavailable, got {:?}", other),
+        }
+        assert!(reject_html(200, true, b"Down for maintenance").is_err());
+        let long_page = format!("<p>{}</p>", "x".repeat(1000));
+        match *reject_html(200, false, long_page.as_bytes())
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::ServiceUnavailable(_, ref snippet) => assert_eq!(snippet.len(), 200),
+            ref other => panic!("expected ServiceUnavailable, got {:?}", other),
+        }
+        assert!(reject_html(200, false, br#" {"data": []}"#).is_ok());
+    }
+
+    #[test]
+    fn limit_body_size() {
+        let body = || stream::iter_ok::<_, hyper::Error>(vec![vec![1u8; 4], vec![2u8; 4]]);
+        assert_eq!(concat_limited(body(), 8).wait().unwrap().len(), 8);
+        match *concat_limited(body(), 7).wait().unwrap_err().kind() {
+            ErrorKind::BodyTooLarge(7) => {}
+            ref other => panic!("expected BodyTooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn access_token_clients() {
+        let core = Core::new().unwrap();
+        let api = ImgurClient::with_access_token(&core.handle(), "xyz".into()).unwrap();
+        let mut headers = Headers::new();
+        api.common_headers().apply(&mut headers);
+        assert_eq!(
+            headers.get::<Authorization<String>>().unwrap().0,
+            "Bearer xyz"
+        );
+    }
+
+    #[test]
+    fn debug_output_hides_deletehashes() {
+        let mut image = image_fixture("abc", 100);
+        image.deletehash = Some("s3cr3t".to_string().into());
+        assert!(!format!("{:?}", image).contains("s3cr3t"));
+        assert_eq!(
+            image.deletehash.as_ref().map(DeleteHash::as_str),
+            Some("s3cr3t")
+        );
+        let json = serde_json::to_string(&image.deletehash).unwrap();
+        assert_eq!(json, r#""s3cr3t""#);
+    }
+
+    #[test]
+    fn debug_output_hides_credentials() {
+        let core = Core::new().unwrap();
+        let api = ImgurClient::builder("secret-id".into())
+            .header("X-Mashape-Key".into(), "secret-key".into())
+            .build(&core.handle())
+            .unwrap();
+        let debug = format!("{:?}", api);
+        assert!(debug.contains("Client-ID(***)"));
+        assert!(debug.contains(r#""X-Mashape-Key": <redacted>"#));
+        assert!(!debug.contains("secret-id") && !debug.contains("secret-key"));
+
+        let builder = ImgurClient::builder("id".into())
+            .auth(Auth::Bearer("secret-token".into()))
+            .header("X-Mashape-Key".into(), "secret-key".into());
+        let debug = format!("{:?}", builder);
+        assert!(debug.contains("Bearer(***)") && !debug.contains("secret-token"));
+        assert!(debug.contains("X-Mashape-Key") && !debug.contains("secret-key"));
+    }
+
+    #[test]
+    fn extra_headers() {
+        let core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .header("X-Mashape-Key".into(), "key".into())
+            .header("Authorization".into(), "Bearer stolen".into())
+            .build(&core.handle())
+            .unwrap();
+        let mut headers = Headers::new();
+        api.common_headers().apply(&mut headers);
+        let value = headers.get_raw("X-Mashape-Key").and_then(|raw| raw.one());
+        assert_eq!(value, Some(&b"key"[..]));
+        assert_eq!(
+            headers.get::<Authorization<String>>().unwrap().0,
+            "Client-ID id"
+        );
+    }
+
+    #[test]
+    fn auth_headers() {
+        assert_eq!(Auth::ClientId("abc".into()).header_value(), "Client-ID abc");
+        assert_eq!(Auth::Bearer("xyz".into()).header_value(), "Bearer xyz");
+    }
+
+    #[test]
+    fn validate_credentials() {
+        let accepted = br#"{"data": {"UserLimit": 2000}, "success": true, "status": 200}"#;
+        let rejected = br#"{"data": {"error": "Invalid client_id", "request": "/3/credits",
+            "method": "GET"}, "success": false, "status": 403}"#;
+        let failed = br#"{"data": {"error": "Over capacity", "request": "/3/credits",
+            "method": "GET"}, "success": false, "status": 500}"#;
+        let check = |body: &[u8]| credentials_accepted(parse_response(body, false).unwrap());
+        assert!(check(accepted).unwrap());
+        assert!(!check(rejected).unwrap());
+        assert!(check(failed).is_err());
+    }
+
+    #[test]
+    fn ignore_unknown_fields() {
+        let body = br#"{
+            "data": {"tags": [{"name": "cats", "ups": 3, "downs": 1, "new_field": true}]},
+            "success": true,
+            "status": 200,
+            "request_id": "abc",
+            "meta": {"page": 1}
+        }"#;
+        let resp: Response<TagVotes> = parse_response(body, false).unwrap();
+        assert_eq!(resp.ok().unwrap().tags[0].name, "cats");
+    }
+
+    #[test]
+    fn classify_transport_errors() {
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        match *transport_error(timeout.into()).kind() {
+            ErrorKind::Timeout => {}
+            ref other => panic!("expected a timeout, got {:?}", other),
+        }
+        let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+        match *transport_error(refused.into()).kind() {
+            ErrorKind::Network => {}
+            ref other => panic!("expected a network error, got {:?}", other),
+        }
+        match *transport_error(hyper::Error::Incomplete).kind() {
+            ErrorKind::Hyper(_) => {}
+            ref other => panic!("expected a hyper error, got {:?}", other),
+        }
+    }
+
+    /// Serves canned HTTP responses on a local port, one connection per request, and
+    /// returns the base URL. `respond` maps a request path to the raw response head
+    /// (status line and headers) and body.
+    fn mock_server<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> (String, Vec<u8>) + Send + 'static,
+    {
+        mock_server_with_requests(move |request| respond(request.split(' ').nth(1).unwrap_or("/")))
+    }
+
+    /// Like `mock_server`, but `respond` gets the whole request, including the body.
+    pub(crate) fn mock_server_with_requests<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> (String, Vec<u8>) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                let mut expected_len = None;
+                while expected_len.map_or(true, |len| request.len() < len) {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    if expected_len.is_none() {
+                        expected_len = request_len(&request);
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).into_owned();
+                let (head, body) = respond(&request);
+                let head = format!(
+                    "{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    head,
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        base
+    }
+
+    /// The length of a request including its body, once the headers are complete.
+    fn request_len(request: &[u8]) -> Option<usize> {
+        let head_len = request.windows(4).position(|w| w == b"\r\n\r\n")? + 4;
+        let head = String::from_utf8_lossy(&request[..head_len]).to_lowercase();
+        let body_len = head
+            .lines()
+            .find(|line| line.starts_with("content-length:"))
+            .and_then(|line| line["content-length:".len()..].trim().parse().ok())
+            .unwrap_or(0);
+        Some(head_len + body_len)
+    }
+
+    fn redirect(location: &str) -> (String, Vec<u8>) {
+        (
+            format!("HTTP/1.1 302 Found\r\nLocation: {}", location),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn downloads_follow_redirects() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        let base = mock_server(|path| match path {
+            "/a/start.png" => redirect("next.png"),
+            "/a/next.png" => redirect("/final.png"),
+            _ => ("HTTP/1.1 200 OK".into(), b"image bytes".to_vec()),
+        });
+        let bytes = core.run(api.download(&format!("{}/a/start.png", base)));
+        assert_eq!(bytes.unwrap(), b"image bytes");
+    }
+
+    #[test]
+    fn downloads_detect_redirect_loops() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        let base = mock_server(|path| match path {
+            "/a.png" => redirect("/b.png"),
+            _ => redirect("/a.png"),
+        });
+        match *core
+            .run(api.download(&format!("{}/a.png", base)))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::RedirectLoop(ref url) => assert!(url.ends_with("/a.png")),
+            ref other => panic!("expected a redirect loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn downloads_limit_redirects() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .max_redirects(1)
+            .build(&core.handle())
+            .unwrap();
+        let base = mock_server(|path| match path {
+            "/a.png" => redirect("/b.png"),
+            "/b.png" => redirect("/c.png"),
+            _ => ("HTTP/1.1 200 OK".into(), Vec::new()),
+        });
+        match *core
+            .run(api.download(&format!("{}/a.png", base)))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::TooManyRedirects(_, 1) => {}
+            ref other => panic!("expected too many redirects, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn drain_waits_for_requests_in_flight() {
+        use std::rc::Rc;
+        use std::time::Duration;
+
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        let base = mock_server(|_| {
+            thread::sleep(Duration::from_millis(50));
+            ("HTTP/1.1 200 OK".into(), b"bytes".to_vec())
+        });
+
+        let finished = Rc::new(RefCell::new(0));
+        for i in 0..3 {
+            let finished = finished.clone();
+            let download = api.clone().download(&format!("{}/{}.png", base, i));
+            core.handle().spawn(download.then(move |result| {
+                assert_eq!(result.unwrap(), b"bytes");
+                *finished.borrow_mut() += 1;
+                Ok(())
+            }));
+        }
+
+        core.run(api.drain()).unwrap();
+        assert_eq!(*finished.borrow(), 3);
+        match *core.run(api.download(&base)).unwrap_err().kind() {
+            ErrorKind::Draining => {}
+            ref other => panic!("expected the client to be draining, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cache")]
+    fn deleted_images_are_fetched_again() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .cache(10, Duration::from_secs(60))
+            .build(&core.handle())
+            .unwrap();
+        let gets = Arc::new(AtomicUsize::new(0));
+        let server_gets = gets.clone();
+        let base = mock_server_with_requests(move |request| {
+            let data = if request.starts_with("DELETE /3/image/hash ") {
+                "true".to_string()
+            } else {
+                let views = server_gets.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut image = image_fixture("abc", 100);
+                image.views = views as u32;
+                serde_json::to_string(&image).unwrap()
+            };
+            let body = format!(r#"{{"data": {}, "success": true, "status": 200}}"#, data);
+            ("HTTP/1.1 200 OK".into(), body.into_bytes())
+        });
+        let base = format!("{}/3", base);
+        let url = || format!("{}/image/abc", base).parse::<Uri>().unwrap();
+
+        let views = |resp: Response<Image>| resp.data.into_result().unwrap().views;
+        assert_eq!(views(core.run(api.cached_get("abc", url())).unwrap()), 1);
+        assert_eq!(views(core.run(api.cached_get("abc", url())).unwrap()), 1);
+        assert_eq!(gets.load(Ordering::SeqCst), 1);
+
+        let deleted = core.run(api.delete_image_at(&base, "hash")).unwrap();
+        assert!(deleted.data.into_result().unwrap());
+        assert_eq!(views(core.run(api.cached_get("abc", url())).unwrap()), 2);
+        assert_eq!(gets.load(Ordering::SeqCst), 2);
+    }
+
+    /// A timer that doesn't wait, recording the delays instead.
+    #[derive(Debug, Default)]
+    struct RecordingTimer(Rc<RefCell<Vec<Duration>>>);
+
+    impl Timer for RecordingTimer {
+        fn delay(&self, delay: Duration) -> Box<dyn Future<Item = (), Error = Error>> {
+            self.0.borrow_mut().push(delay);
+            Box::new(future::ok(()))
+        }
+    }
+
+    /// A mock server answering the first request with a 503 and the rest with `data`.
+    fn flaky_server(data: &'static str) -> String {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let attempts = AtomicUsize::new(0);
+        mock_server(move |_| {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                ("HTTP/1.1 503 Service Unavailable".into(), Vec::new())
+            } else {
+                let body = format!(r#"{{"data": {}, "success": true, "status": 200}}"#, data);
+                ("HTTP/1.1 200 OK".into(), body.into_bytes())
+            }
+        })
+    }
+
+    #[test]
+    fn streaming_uses_the_shared_request_path() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .retry_policy(RetryPolicy::new(1).base_delay(Duration::from_millis(1)))
+            .build(&core.handle())
+            .unwrap();
+        let attempts = AtomicUsize::new(0);
+        let base = mock_server(move |path| {
+            if path == "/3/error" {
+                let page = b"<html><body>Over capacity</body></html>".to_vec();
+                return (
+                    "HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/html".into(),
+                    page,
+                );
+            }
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return ("HTTP/1.1 503 Service Unavailable".into(), Vec::new());
+            }
+            let body = br#"{"data": [1, 2, 3], "success": true, "status": 200}"#;
+            let head = "HTTP/1.1 200 OK\r\nX-RateLimit-UserRemaining: 42";
+            (head.into(), body.to_vec())
+        });
+
+        let url: Uri = format!("{}/3/images", base).parse().unwrap();
+        let items: Vec<u32> = core.run(api.stream_data_array(url).collect()).unwrap();
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!(
+            api.rate_limit().and_then(|info| info.user_remaining),
+            Some(42)
+        );
+
+        let url: Uri = format!("{}/3/error", base).parse().unwrap();
+        let failed = core.run(api.stream_data_array::<u32>(url).collect());
+        match *failed.unwrap_err().kind() {
+            ErrorKind::ServiceUnavailable(503, ref snippet) => assert!(snippet.contains("Over")),
+            ref other => panic!("expected ServiceUnavailable, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn throttle_every_attempt_once_sent() {
+        let mut core = Core::new().unwrap();
+        let connector = HttpsConnector::new(1, &core.handle()).unwrap();
+        let client = Client::configure()
+            .connector(connector)
+            .build(&core.handle());
+        let timer = RecordingTimer::default();
+        let delays = timer.0.clone();
+        let throttle = Throttle::new(10).fallback_window(Duration::from_secs(3600));
+        let api = ImgurClient::builder("id".into())
+            .retry_policy(RetryPolicy::new(1).base_delay(Duration::from_millis(1)))
+            .throttle(throttle)
+            .build_with_timer(client, timer);
+        *api.rate_limit.lock().unwrap() = Some(RateLimitInfo {
+            user_remaining: Some(0),
+            ..RateLimitInfo::default()
+        });
+        let url: Uri = format!("{}/3/credits", flaky_server("1")).parse().unwrap();
+
+        // Never polled, so it doesn't take the throttle's first slot.
+        drop(api.get_with_header::<u32>(url.clone()));
+        let resp: Response<u32> = core.run(api.get_with_header(url)).unwrap();
+        assert_eq!(resp.ok(), Some(&1));
+
+        // The retry delay, then the throttle's delay for the retry itself.
+        let delays = delays.borrow();
+        assert_eq!(delays.len(), 2, "{:?}", delays);
+        assert!(delays[0] <= Duration::from_millis(1));
+        assert!(delays[1] > Duration::from_secs(3500));
+    }
+
+    #[test]
+    fn build_with_timer_and_executor() {
+        let mut core = Core::new().unwrap();
+        let connector = HttpsConnector::new(1, &core.handle()).unwrap();
+        let client = Client::configure()
+            .connector(connector)
+            .executor(core.handle());
+        let timer = RecordingTimer::default();
+        let delays = timer.0.clone();
+        let api = ImgurClient::builder("id".into())
+            .retry_policy(RetryPolicy::new(1))
+            .build_with_timer(client, timer);
+
+        let url: Uri = format!("{}/3/credits", flaky_server("1")).parse().unwrap();
+        let resp: Response<u32> = core.run(api.get_with_header(url)).unwrap();
+        assert_eq!(resp.ok(), Some(&1));
+        assert_eq!(delays.borrow().len(), 1);
+    }
+
+    #[test]
+    fn drain_skips_abandoned_coalesced_gets() {
+        use std::time::Duration;
+
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .coalesce_requests(true)
+            .build(&core.handle())
+            .unwrap();
+        let base = mock_server(|_| {
+            thread::sleep(Duration::from_millis(50));
+            ("HTTP/1.1 200 OK".into(), b"{}".to_vec())
+        });
+
+        let url: Uri = format!("{}/3/image/abc", base).parse().unwrap();
+        let get = api.get_with_header::<Image>(url);
+        drop(get);
+        core.run(api.drain()).unwrap();
+        assert_eq!(api.in_flight_gets.as_ref().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn debug_requests_redact_credentials() {
+        let core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "secret".into()).unwrap();
+        let request = api.debug_request_for_image("a b");
+        assert_eq!(request.method(), &Method::Get);
+        assert_eq!(
+            request.uri().as_ref(),
+            "https://api.imgur.com/3/image/a%20b"
+        );
+        let authorization = request.headers().get::<Authorization<String>>().unwrap();
+        assert_eq!(authorization.0, "Client-ID <redacted>");
+    }
+
+    #[test]
+    fn user_requests_require_bearer_auth() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        match *core.run(api.notifications(false)).unwrap_err().kind() {
+            ErrorKind::Unauthorized => {}
+            ref other => panic!("expected Unauthorized, got {:?}", other),
+        }
+        assert!(core.run(api.conversations()).is_err());
+        assert!(core.run(api.account_images("me", 0)).is_err());
+        let favorites = api.account_favorites("Me", 0, FavoritesSort::Oldest);
+        assert!(core.run(favorites).is_err());
+        assert!(core.run(api.favorite_image("PE2NI")).is_err());
+        assert!(core.run(api.account_image("me", "PE2NI")).is_err());
+        assert!(core.run(api.account_image_count("ME")).is_err());
+        assert!(core.run(api.ensure_unfavorited("PE2NI")).is_err());
+        assert!(api.require_bearer().is_err());
+
+        let user = ImgurClient::builder("id".into())
+            .auth(Auth::Bearer("token".into()))
+            .build(&core.handle())
+            .unwrap();
+        assert!(user.require_bearer().is_ok());
+    }
+
+    #[test]
+    fn refuse_jobs_over_budget() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::builder("id".into())
+            .refuse_over_budget(true)
+            .build(&core.handle())
+            .unwrap();
+        *api.rate_limit.lock().unwrap() = Some(RateLimitInfo {
+            user_remaining: Some(100),
+            client_remaining: Some(3),
+            ..RateLimitInfo::default()
+        });
+        let hashes = ["a", "b", "c", "d"];
+        match *core.run(api.delete_images(&hashes, 2)).unwrap_err().kind() {
+            ErrorKind::InsufficientCredits(4, 3) => {}
+            ref other => panic!("expected the job to be refused, got {:?}", other),
+        }
+        assert!(api.check_budget(3).is_ok());
+
+        let lenient = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        *lenient.rate_limit.lock().unwrap() = *api.rate_limit.lock().unwrap();
+        assert!(lenient.check_budget(4).is_ok());
+    }
+
+    /// A small deterministic random number generator (an LCG) for the fuzz tests, to
+    /// avoid a dependency on `proptest`.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (self.0 >> 33) as usize % n
+        }
+    }
+
+    /// Every prefix of `body`, plus random edits of it using bytes that are likely to
+    /// confuse a JSON parser.
+    fn mutations(body: &[u8], rng: &mut Lcg) -> Vec<Vec<u8>> {
+        const BYTES: &[u8] = b"{}[]\":,.-+0123456789eEtrufalsn \\\xff";
+        let mut bodies: Vec<Vec<u8>> = (0..body.len()).map(|end| body[..end].to_vec()).collect();
+        for _ in 0..1000 {
+            let mut mutated = body.to_vec();
+            for _ in 0..1 + rng.below(4) {
+                let at = rng.below(mutated.len());
+                match rng.below(3) {
+                    0 => mutated[at] = BYTES[rng.below(BYTES.len())],
+                    1 => {
+                        mutated.remove(at);
+                    }
+                    _ => mutated.insert(at, BYTES[rng.below(BYTES.len())]),
+                }
+            }
+            bodies.push(mutated);
+        }
+        bodies
+    }
+
+    #[test]
+    fn malformed_responses_never_panic() {
+        let wrap =
+            |data: String| format!(r#"{{"data": {}, "success": true, "status": 200}}"#, data);
+        let image = wrap(serde_json::to_string(&image_fixture("PE2NI", 1)).unwrap());
+        let album = wrap(serde_json::to_string(&album_fixture("cXz3n", 0)).unwrap());
+        let gallery = wrap(GALLERY_JSON.to_string());
+
+        let mut rng = Lcg(42);
+        for body in &[image, album, gallery] {
+            assert!(parse_response::<serde_json::Value>(body.as_bytes(), false).is_ok());
+            for mutated in mutations(body.as_bytes(), &mut rng) {
+                let _ = parse_response::<Image>(&mutated, true);
+                let _ = parse_response::<Album>(&mutated, true);
+                let _ = parse_response::<Vec<GalleryItem>>(&mutated, true);
+                let mut parser = streaming::DataArrayParser::<GalleryItem>::new();
+                for chunk in mutated.chunks(16) {
+                    let _ = parser.feed(chunk);
+                }
+                let _ = parser.finish();
+            }
+        }
+    }
+
+    #[test]
+    fn any_id_builds_a_valid_request() {
+        let core = Core::new().unwrap();
+        let anonymous = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        // Methods requiring an access token fail before building their URL otherwise.
+        let user = ImgurClient::with_access_token(&core.handle(), "token".into()).unwrap();
+        let ids = [
+            "",
+            "a b",
+            "ä/ö",
+            "\"<>{}|^`",
+            "a/b?c#d",
+            "\u{0}\n",
+            "%zz",
+            "me",
+        ];
+        for api in &[anonymous, user] {
+            for id in &ids {
+                // Building the futures parses their URLs, which used to panic on such ids.
+                let _ = api.image(*id);
+                let _ = api.album(*id);
+                let _ = api.album_images(*id);
+                let _ = api.album_image(*id, *id);
+                let _ = api.album_images_streaming(*id);
+                let _ = api.delete_image(id);
+                let _ = api.account_images(id, 0);
+                let _ = api.account_image_count(id);
+                let _ = api.account_image(id, *id);
+                let _ = api.account_submissions(id, 0);
+                let _ = api.account_trophies(id);
+                let _ = api.account(id);
+                let _ = api.account_favorites(id, 0, FavoritesSort::Newest);
+                let _ = api.account_folders(id);
+                let _ = api.folder_images(id, 1, 0);
+                let _ = api.gallery_image(*id);
+                let _ = api.gallery_item_tags(id);
+                let _ = api.add_gallery_tags(id, &["tag"]);
+                let _ = api.subreddit_gallery(id, Sort::Top, Window::All, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_hosts_are_network_errors() {
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "id".into()).unwrap();
+        for url in &[
+            "http://127.0.0.1:1/image.png",
+            "http://imgur.invalid/image.png",
+        ] {
+            match *core.run(api.download(url)).unwrap_err().kind() {
+                ErrorKind::Network => {}
+                ref other => panic!("expected a network error for {}, got {:?}", url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_ids_and_urls() {
+        let cases = [
+            ("PE2NI", ResourceKind::Image, "PE2NI"),
+            ("PE2NI.jpg", ResourceKind::Image, "PE2NI"),
+            ("https://imgur.com/PE2NI", ResourceKind::Image, "PE2NI"),
+            ("http://i.imgur.com/PE2NI.jpg", ResourceKind::Image, "PE2NI"),
+            (
+                "https://i.imgur.com/PE2NI.gifv?1",
+                ResourceKind::Image,
+                "PE2NI",
+            ),
+            ("imgur.com/a/cXz3n", ResourceKind::Album, "cXz3n"),
+            ("https://imgur.com/a/cXz3n/", ResourceKind::Album, "cXz3n"),
+            ("https://imgur.com/a/cXz3n#0", ResourceKind::Album, "cXz3n"),
+            (
+                "https://m.imgur.com/gallery/abc123?ref=foo",
+                ResourceKind::Gallery,
+                "abc123",
+            ),
+            (
+                "https://imgur.com/a/my-cat-does-a-flip-Xk9Qw2L",
+                ResourceKind::Album,
+                "Xk9Qw2L",
+            ),
+            (
+                "https://imgur.com/gallery/flip-Xk9Qw2L#/t/cats",
+                ResourceKind::Gallery,
+                "Xk9Qw2L",
+            ),
+        ];
+        for &(input, kind, id) in &cases {
+            assert_eq!(
+                parse_id(input).unwrap(),
+                (kind, id.to_string()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_ids() {
+        let cases = [
+            "",
+            "https://example.com/abc123",
+            "https://imgur.com/",
+            "https://imgur.com/user/foo/favorites",
+            "https://imgur.com/a/trailing-dash-",
+            "abc-123",
+            "https://imgur.com",
+            "imgur.com",
+            "i.imgur.com",
+            "https://i.imgur.com?foo=bar",
+            "abc.def.jpg",
+            "abc.",
+            "https://i.imgur.com/abc.tar.gz",
+        ];
+        for input in &cases {
+            assert!(parse_id(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn typed_ids() {
+        let image_id = ImageId::from("PE2NI");
+        let album_id = AlbumId::from(String::from("cXz3n"));
+        assert_eq!(image_id.to_string(), "PE2NI");
+        assert_eq!(album_id.as_album_id(), "cXz3n");
+        assert_eq!("PE2NI".as_image_id(), image_id.as_str());
+    }
+
+    #[test]
+    fn expect_resource_kind() {
+        assert!(expect_kind("https://imgur.com/a/cXz3n", ResourceKind::Image).is_err());
+        assert_eq!(
+            expect_kind("https://imgur.com/gallery/cXz3n", ResourceKind::Album).unwrap(),
+            "cXz3n"
+        );
     }
 }
@@ -0,0 +1,54 @@
+//! Conversations and messages between imgur users. All of these require an access
+//! token (`Auth::Bearer`), and fail with `ErrorKind::Unauthorized` without making a
+//! request otherwise.
+
+use futures::future::{self, Either};
+use futures::Future;
+
+use {Error, ImgurClient, Response, API};
+
+impl ImgurClient {
+    /// Gets the current user's conversations (`GET /conversations`).
+    pub fn conversations(&self) -> impl Future<Item = Response<Vec<Conversation>>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/conversations", API).parse().unwrap();
+        Either::B(self.get_with_header(url))
+    }
+}
+
+/// A conversation with another user.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: u64,
+    pub with_account: String,
+    pub with_account_id: u64,
+    pub last_message_preview: String,
+    pub message_count: u32,
+    pub datetime: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_conversations() {
+        let body = r#"{
+            "data": [{
+                "id": 188129, "last_message_preview": "Hello there", "datetime": 1519741331,
+                "with_account_id": 384077, "with_account": "someone", "message_count": 3
+            }],
+            "success": true,
+            "status": 200
+        }"#;
+        let resp: Response<Vec<Conversation>> = serde_json::from_str(body).unwrap();
+        let conversations = resp.data.into_result().unwrap();
+        assert_eq!(conversations[0].with_account, "someone");
+        assert_eq!(conversations[0].message_count, 3);
+    }
+}
@@ -0,0 +1,94 @@
+//! A minimal `multipart/form-data` body builder, just enough for uploads.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Builds a `multipart/form-data` request body in memory.
+#[derive(Debug)]
+pub(crate) struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    pub(crate) fn new() -> Multipart {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64 ^ d.as_secs())
+            .unwrap_or(0);
+        Multipart::with_boundary(format!("------------------------imgur-rs{:016x}", nanos))
+    }
+
+    pub(crate) fn with_boundary(boundary: String) -> Multipart {
+        Multipart {
+            boundary,
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a plain text field.
+    pub(crate) fn text(&mut self, name: &str, value: &str) {
+        self.start_part();
+        self.push(&format!(
+            "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+            name
+        ));
+        self.push(value);
+        self.push("\r\n");
+    }
+
+    /// Adds a file field.
+    pub(crate) fn file(&mut self, name: &str, filename: &str, content_type: &str, bytes: &[u8]) {
+        self.start_part();
+        self.push(&format!(
+            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+            name,
+            filename.replace('"', "")
+        ));
+        self.push(&format!("Content-Type: {}\r\n\r\n", content_type));
+        self.body.extend_from_slice(bytes);
+        self.push("\r\n");
+    }
+
+    /// The value for the request's `Content-Type` header.
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Closes the body and returns it.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let closing = format!("--{}--\r\n", self.boundary);
+        self.push(&closing);
+        self.body
+    }
+
+    fn start_part(&mut self) {
+        let opening = format!("--{}\r\n", self.boundary);
+        self.push(&opening);
+    }
+
+    fn push(&mut self, s: &str) {
+        self.body.extend_from_slice(s.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_form_body() {
+        let mut form = Multipart::with_boundary("XYZ".into());
+        form.text("title", "Hello");
+        form.file("image", "a.png", "image/png", b"\x89PNG");
+        assert_eq!(form.content_type(), "multipart/form-data; boundary=XYZ");
+        let expected: &[u8] = b"--XYZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            Hello\r\n\
+            --XYZ\r\n\
+            Content-Disposition: form-data; name=\"image\"; filename=\"a.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            \x89PNG\r\n\
+            --XYZ--\r\n";
+        assert_eq!(form.finish(), expected);
+    }
+}
@@ -0,0 +1,126 @@
+//! Notifications about comment replies and messages. All of these require an access
+//! token (`Auth::Bearer`), and fail with `ErrorKind::Unauthorized` without making a
+//! request otherwise.
+
+use futures::future::{self, Either};
+use futures::Future;
+
+use {Comment, Error, ImgurClient, RequestBody, Response, API};
+
+impl ImgurClient {
+    /// Gets the current user's notifications (`GET /notification?new=<only_new>`). With
+    /// `only_new` set, notifications that were already marked as viewed are left out.
+    pub fn notifications(
+        &self,
+        only_new: bool,
+    ) -> impl Future<Item = Response<Notifications>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/notification?new={}", API, only_new)
+            .parse()
+            .unwrap();
+        Either::B(self.get_with_header(url))
+    }
+
+    /// Marks notifications as viewed (`POST /notification`), e.g. to clear a badge once
+    /// the user has read them.
+    pub fn mark_notifications_viewed(
+        &self,
+        ids: &[u64],
+    ) -> impl Future<Item = Response<bool>, Error = Error> {
+        if let Err(e) = self.require_bearer() {
+            return Either::A(future::err(e));
+        }
+        let url = format!("{}/notification", API).parse().unwrap();
+        Either::B(self.post_with_header(url, viewed_form(ids)))
+    }
+}
+
+fn viewed_form(ids: &[u64]) -> RequestBody {
+    let ids: Vec<String> = ids.iter().map(u64::to_string).collect();
+    RequestBody::urlencoded(&[("ids", &ids.join(","))])
+}
+
+/// The current user's notifications, split up the way imgur returns them.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notifications {
+    pub replies: Vec<Notification<Comment>>,
+    pub messages: Vec<Notification<MessageNotification>>,
+}
+
+/// A single notification. `content` is a `Comment` for replies and a
+/// `MessageNotification` for messages.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification<T> {
+    pub id: u64,
+    pub account_id: u64,
+    #[serde(deserialize_with = "::lenient::bool_or_int")]
+    pub viewed: bool,
+    pub content: T,
+}
+
+/// The content of a message notification.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageNotification {
+    pub id: u64,
+    pub from: String,
+    pub account_id: u64,
+    pub with_account: u64,
+    pub last_message: String,
+    pub message_num: u32,
+    pub datetime: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    const NOTIFICATIONS_JSON: &str = r#"{
+        "data": {
+            "replies": [{
+                "id": 8238, "account_id": 384077, "viewed": false,
+                "content": {
+                    "id": 487053466, "image_id": "PE2NI", "comment": "Nice!",
+                    "author": "someone", "author_id": 1234, "on_album": false,
+                    "album_cover": null, "ups": 2, "downs": 0, "points": 2,
+                    "datetime": 1519741331, "parent_id": 487053000, "deleted": false,
+                    "vote": null, "children": []
+                }
+            }],
+            "messages": [{
+                "id": 8239, "account_id": 384077, "viewed": true,
+                "content": {
+                    "id": 188129, "from": "someone", "account_id": 1234,
+                    "with_account": 384077, "last_message": "Hello there",
+                    "message_num": 3, "datetime": 1519741400
+                }
+            }]
+        },
+        "success": true,
+        "status": 200
+    }"#;
+
+    #[test]
+    fn viewed_ids_are_comma_joined() {
+        assert_eq!(viewed_form(&[8238, 8239]).bytes, b"ids=8238%2C8239");
+    }
+
+    #[test]
+    fn deserialize_notifications() {
+        let resp: Response<Notifications> = serde_json::from_str(NOTIFICATIONS_JSON).unwrap();
+        let notifications = resp.data.into_result().unwrap();
+        assert_eq!(notifications.replies[0].content.comment, "Nice!");
+        assert!(!notifications.replies[0].viewed);
+        assert_eq!(
+            notifications.messages[0].content.last_message,
+            "Hello there"
+        );
+        assert!(notifications.messages[0].viewed);
+    }
+}
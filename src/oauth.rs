@@ -0,0 +1,283 @@
+//! Logging in users with OAuth2, to get the access token for `Auth::Bearer`.
+
+use std::fmt;
+
+use futures::future::{self, Either};
+use futures::Future;
+use hyper::Method;
+use serde_json;
+
+use {build_request, concat_limited, form, parse_response, transport_error};
+use {Auth, Error, ImgurClient, RequestBody, Response, Result};
+
+const AUTHORIZE: &str = "https://api.imgur.com/oauth2/authorize";
+const TOKEN: &str = "https://api.imgur.com/oauth2/token";
+
+/// What imgur hands back once the user allowed access, for `authorization_url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseType {
+    /// The access token itself, in the fragment of the redirect URL.
+    Token,
+    /// A code to exchange for an access token, as a query parameter of the redirect
+    /// URL.
+    Code,
+    /// A PIN for the user to copy into the app, for apps without a redirect URL.
+    Pin,
+}
+
+impl ResponseType {
+    fn as_str(&self) -> &str {
+        match *self {
+            ResponseType::Token => "token",
+            ResponseType::Code => "code",
+            ResponseType::Pin => "pin",
+        }
+    }
+}
+
+/// The URL to send users to for allowing the application `client_id` access to their
+/// account. imgur passes `state` back along with the response, e.g. to tie it to the
+/// login attempt.
+pub fn authorization_url(
+    client_id: &str,
+    response_type: ResponseType,
+    state: Option<&str>,
+) -> String {
+    let mut pairs = vec![
+        ("client_id", client_id),
+        ("response_type", response_type.as_str()),
+    ];
+    if let Some(state) = state {
+        pairs.push(("state", state));
+    }
+    format!("{}?{}", AUTHORIZE, form::urlencoded(&pairs))
+}
+
+impl ImgurClient {
+    /// Exchanges the PIN a user copied from the page at
+    /// `authorization_url(.., ResponseType::Pin, ..)` for an access token
+    /// (`POST /oauth2/token` with `grant_type=pin`). Needs a client authenticated with
+    /// its client id (`Auth::ClientId`), which has to match the one in the URL.
+    ///
+    /// The token endpoint isn't part of the versioned API, so this isn't retried or
+    /// throttled like other requests.
+    pub fn exchange_pin(
+        &self,
+        pin: &str,
+        client_secret: &str,
+    ) -> impl Future<Item = TokenResponse, Error = Error> {
+        self.request_token(TOKEN, client_secret, &pin_grant(pin))
+    }
+
+    /// Exchanges the code imgur added to the redirect URL after a user visited
+    /// `authorization_url(.., ResponseType::Code, ..)` for an access token
+    /// (`POST /oauth2/token` with `grant_type=authorization_code`). Like `exchange_pin`,
+    /// this needs a client authenticated with its client id.
+    pub fn exchange_code(
+        &self,
+        code: &str,
+        client_secret: &str,
+    ) -> impl Future<Item = TokenResponse, Error = Error> {
+        self.request_token(TOKEN, client_secret, &code_grant(code))
+    }
+
+    /// Sends `grant` to the token endpoint at `url` along with the client's
+    /// credentials.
+    fn request_token(
+        &self,
+        url: &str,
+        client_secret: &str,
+        grant: &[(&str, &str)],
+    ) -> impl Future<Item = TokenResponse, Error = Error> {
+        let body = match token_form(&self.auth, client_secret, grant) {
+            Ok(body) => body,
+            Err(e) => return Either::A(future::err(e)),
+        };
+        let url = match url.parse() {
+            Ok(url) => url,
+            Err(e) => return Either::A(future::err(Error::from(hyper::Error::from(e)))),
+        };
+        let request = build_request(Method::Post, url, &self.common_headers(), Some(&body));
+        let max_body_bytes = self.max_body_bytes;
+        let work = self
+            .client
+            .request(request)
+            .map_err(transport_error)
+            .and_then(move |resp| {
+                let success = resp.status().is_success();
+                concat_limited(resp.body(), max_body_bytes)
+                    .and_then(move |body| parse_token(success, &body))
+            });
+        Either::B(self.tracked(work))
+    }
+}
+
+fn pin_grant(pin: &str) -> [(&str, &str); 2] {
+    [("grant_type", "pin"), ("pin", pin)]
+}
+
+fn code_grant(code: &str) -> [(&str, &str); 2] {
+    [("grant_type", "authorization_code"), ("code", code)]
+}
+
+/// The form for a token request, identifying the application by the client id of
+/// `auth`.
+fn token_form(auth: &Auth, client_secret: &str, grant: &[(&str, &str)]) -> Result<RequestBody> {
+    let client_id = match *auth {
+        Auth::ClientId(ref client_id) => client_id,
+        Auth::Bearer(_) => {
+            return Err("requesting an access token requires a client id (Auth::ClientId)".into())
+        }
+    };
+    let mut pairs = vec![
+        ("client_id", client_id.as_str()),
+        ("client_secret", client_secret),
+    ];
+    pairs.extend_from_slice(grant);
+    Ok(RequestBody::urlencoded(&pairs))
+}
+
+/// Reads the response of the token endpoint, which unlike the rest of the API only
+/// wraps errors in the usual `data` object.
+fn parse_token(success: bool, body: &[u8]) -> Result<TokenResponse> {
+    if success {
+        Ok(serde_json::from_slice(body)?)
+    } else {
+        let response: Response<TokenResponse> = parse_response(body, false)?;
+        response.data.into_result()
+    }
+}
+
+/// The access token imgur grants when a user logs in, for `Auth::Bearer`. Its `Debug`
+/// output leaves out the tokens, so it can be logged.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    /// The token for `Auth::Bearer`.
+    pub access_token: String,
+    /// The token for getting a new access token once this one expires.
+    pub refresh_token: String,
+    /// How long the access token is valid, in seconds.
+    pub expires_in: u64,
+    /// Always `"bearer"`.
+    pub token_type: String,
+    /// The scope of the access, if any.
+    pub scope: Option<String>,
+    /// The id of the user's account.
+    pub account_id: u64,
+    /// The user's username.
+    pub account_username: String,
+}
+
+impl fmt::Debug for TokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TokenResponse")
+            .field("access_token", &"***")
+            .field("refresh_token", &"***")
+            .field("expires_in", &self.expires_in)
+            .field("token_type", &self.token_type)
+            .field("scope", &self.scope)
+            .field("account_id", &self.account_id)
+            .field("account_username", &self.account_username)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ErrorKind;
+
+    #[test]
+    fn authorization_urls() {
+        assert_eq!(
+            authorization_url("abc123", ResponseType::Pin, None),
+            "https://api.imgur.com/oauth2/authorize?client_id=abc123&response_type=pin"
+        );
+        assert_eq!(
+            authorization_url("abc123", ResponseType::Token, Some("a b&c")),
+            "https://api.imgur.com/oauth2/authorize?client_id=abc123&response_type=token\
+             &state=a%20b%26c"
+        );
+    }
+
+    #[test]
+    fn token_forms() {
+        let auth = Auth::ClientId("abc123".into());
+        let grant = code_grant("c/d");
+        let form = token_form(&auth, "s3cr3t", &grant).unwrap();
+        assert_eq!(
+            form.bytes,
+            &b"client_id=abc123&client_secret=s3cr3t&grant_type=authorization_code&code=c%2Fd"[..]
+        );
+        assert!(token_form(&Auth::Bearer("token".into()), "s3cr3t", &grant).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "tokio-core")]
+    fn token_requests() {
+        use std::sync::mpsc;
+
+        use tests::mock_server_with_requests;
+        use tokio_core::reactor::Core;
+
+        let mut core = Core::new().unwrap();
+        let api = ImgurClient::new(&core.handle(), "abc123".into()).unwrap();
+        let (send, requests) = mpsc::channel();
+        let base = mock_server_with_requests(move |request| {
+            send.send(request.to_string()).unwrap();
+            let body = br#"{"access_token": "a", "expires_in": 3600, "token_type": "bearer",
+                "scope": null, "refresh_token": "r", "account_id": 1,
+                "account_username": "someone"}"#;
+            ("HTTP/1.1 200 OK".into(), body.to_vec())
+        });
+        let url = format!("{}/oauth2/token", base);
+
+        let grants = [
+            (pin_grant("1234"), "grant_type=pin&pin=1234"),
+            (
+                code_grant("c/d"),
+                "grant_type=authorization_code&code=c%2Fd",
+            ),
+        ];
+        for &(ref grant, expected) in &grants {
+            let token = core.run(api.request_token(&url, "s3cr3t", grant)).unwrap();
+            assert_eq!(token.account_username, "someone");
+
+            let request = requests.recv().unwrap();
+            let (head, body) = request.split_at(request.find("\r\n\r\n").unwrap() + 4);
+            assert!(
+                head.starts_with("POST /oauth2/token HTTP/1.1\r\n"),
+                "{}",
+                head
+            );
+            assert!(
+                head.lines()
+                    .any(|line| line == "Content-Type: application/x-www-form-urlencoded"),
+                "{}",
+                head
+            );
+            assert_eq!(
+                body,
+                format!("client_id=abc123&client_secret=s3cr3t&{}", expected)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_token_responses() {
+        let body = br#"{"access_token": "secret-access", "expires_in": 315360000,
+            "token_type": "bearer", "scope": null, "refresh_token": "secret-refresh",
+            "account_id": 384077, "account_username": "someone"}"#;
+        let token = parse_token(true, body).unwrap();
+        assert_eq!(token.access_token, "secret-access");
+        assert_eq!(token.account_username, "someone");
+        assert!(!format!("{:?}", token).contains("secret"));
+
+        let body = br#"{"data": {"error": "Invalid Pin", "request": "/oauth2/token",
+            "method": "POST"}, "success": false, "status": 400}"#;
+        match *parse_token(false, body).unwrap_err().kind() {
+            ErrorKind::Imgur(ref e) => assert_eq!(e.error, "Invalid Pin"),
+            ref other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+}
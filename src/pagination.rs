@@ -0,0 +1,279 @@
+//! Walking paged endpoints.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use futures::{future, Async, Future, Poll, Stream};
+
+use {Error, Response, ResponseFutureExt};
+
+/// One page of a paged endpoint, e.g. from `ImgurClient::gallery_page`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    /// The items on the page.
+    pub items: Vec<T>,
+    /// Whether the page was full, so that the next page may have more items. imgur
+    /// doesn't report the number of pages, so a full last page still claims there
+    /// may be more; the page after it is then empty.
+    pub has_more: bool,
+}
+
+/// Resolves to the page `fetch` gets, with `has_more` set if it holds at least
+/// `page_size` items.
+pub(crate) fn page<T, R>(fetch: R, page_size: usize) -> impl Future<Item = Page<T>, Error = Error>
+where
+    R: Future<Item = Response<Vec<T>>, Error = Error>,
+{
+    fetch.into_data().map(move |items| Page {
+        has_more: items.len() >= page_size,
+        items,
+    })
+}
+
+/// Walks a paged endpoint, fetching page 0, 1, 2, ... until a page comes back empty or
+/// fails. Pages are only fetched when asked for, either a page at a time with
+/// `next_page` or an item at a time through the `Stream` impl.
+///
+/// `fetch` gets the number of the page to fetch and returns the request for it, e.g.
+/// `move |page| client.account_images("me", page)`.
+///
+/// There's no limit on the number of pages by default, so collecting a huge feed can
+/// use up the rate limit; `max_pages` and `max_items` stop the walk early.
+pub struct Paginator<T, F, R> {
+    fetch: F,
+    /// The page to fetch next, or `None` once the end was reached.
+    next: Option<u32>,
+    pages_left: Option<u32>,
+    items_left: Option<usize>,
+    pending: Option<R>,
+    /// Items of the current page not yet yielded by the stream.
+    buffer: VecDeque<T>,
+}
+
+impl<T, F, R> Paginator<T, F, R>
+where
+    F: FnMut(u32) -> R,
+    R: Future<Item = Response<Vec<T>>, Error = Error>,
+{
+    /// A paginator starting at page 0.
+    pub fn new(fetch: F) -> Paginator<T, F, R> {
+        Paginator {
+            fetch,
+            next: Some(0),
+            pages_left: None,
+            items_left: None,
+            pending: None,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Fetches at most `max_pages` pages. Each page is a request, so this also caps
+    /// the credits the walk costs.
+    pub fn max_pages(mut self, max_pages: u32) -> Paginator<T, F, R> {
+        self.pages_left = Some(max_pages);
+        self
+    }
+
+    /// Stops after `max_items` items, without fetching the rest of the pages. The last
+    /// page is cut short if needed.
+    pub fn max_items(mut self, max_items: usize) -> Paginator<T, F, R> {
+        self.items_left = Some(max_items);
+        self
+    }
+
+    /// Resolves to the next page, or to `None` after the last one, along with the
+    /// paginator to continue with. Items the stream buffered but didn't yield yet
+    /// come first, as a page of their own.
+    pub fn next_page(self) -> impl Future<Item = (Option<Vec<T>>, Self), Error = Error> {
+        let mut paginator = Some(self);
+        future::poll_fn(move || {
+            let page = match paginator.as_mut().unwrap().poll_page()? {
+                Async::Ready(page) => page,
+                Async::NotReady => return Ok(Async::NotReady),
+            };
+            Ok(Async::Ready((page, paginator.take().unwrap())))
+        })
+    }
+
+    fn poll_page(&mut self) -> Poll<Option<Vec<T>>, Error> {
+        if !self.buffer.is_empty() {
+            return Ok(Async::Ready(Some(self.buffer.drain(..).collect())));
+        }
+        let page = match self.next {
+            Some(_) if self.pages_left == Some(0) || self.items_left == Some(0) => {
+                return Ok(Async::Ready(None))
+            }
+            Some(page) => page,
+            None => return Ok(Async::Ready(None)),
+        };
+        if self.pending.is_none() {
+            self.pending = Some((self.fetch)(page));
+        }
+        let result = match self.pending.as_mut().unwrap().poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(resp)) => resp.data.into_result(),
+            Err(e) => Err(e),
+        };
+        self.pending = None;
+        self.next = None;
+        let mut items = result?;
+        if items.is_empty() {
+            return Ok(Async::Ready(None));
+        }
+        self.next = Some(page + 1);
+        if let Some(ref mut pages_left) = self.pages_left {
+            *pages_left -= 1;
+        }
+        if let Some(ref mut items_left) = self.items_left {
+            items.truncate(*items_left);
+            *items_left -= items.len();
+        }
+        Ok(Async::Ready(Some(items)))
+    }
+}
+
+impl<T, F, R> Stream for Paginator<T, F, R>
+where
+    F: FnMut(u32) -> R,
+    R: Future<Item = Response<Vec<T>>, Error = Error>,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<T>, Error> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Ok(Async::Ready(Some(item)));
+            }
+            match self.poll_page()? {
+                Async::Ready(Some(items)) => self.buffer.extend(items),
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+impl<T, F, R> fmt::Debug for Paginator<T, F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("next", &self.next)
+            .field("pages_left", &self.pages_left)
+            .field("items_left", &self.items_left)
+            .field("pending", &self.pending.is_some())
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ResponseData;
+
+    /// A fake paged endpoint serving `pages`, then empty pages.
+    fn fake_pages(
+        pages: Vec<Vec<u32>>,
+    ) -> impl FnMut(u32) -> future::FutureResult<Response<Vec<u32>>, Error> {
+        move |page| {
+            let items = pages.get(page as usize).cloned().unwrap_or_default();
+            future::ok(Response {
+                status: 200,
+                success: true,
+                data: ResponseData::Success(items),
+                raw: None,
+                rate_limit: None,
+                server_timing: None,
+            })
+        }
+    }
+
+    #[test]
+    fn full_pages_have_more() {
+        let mut fetch = fake_pages(vec![vec![1, 2], vec![3]]);
+        let first = page(fetch(0), 2).wait().unwrap();
+        assert_eq!(first.items, vec![1, 2]);
+        assert!(first.has_more);
+        assert!(!page(fetch(1), 2).wait().unwrap().has_more);
+    }
+
+    #[test]
+    fn walks_until_empty_page() {
+        let items = Paginator::new(fake_pages(vec![vec![1, 2], vec![3], vec![4, 5]]))
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(items, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn hands_out_whole_pages() {
+        let paginator = Paginator::new(fake_pages(vec![vec![1, 2], vec![3]]));
+        let (first, paginator) = paginator.next_page().wait().unwrap();
+        assert_eq!(first, Some(vec![1, 2]));
+        let (second, paginator) = paginator.next_page().wait().unwrap();
+        assert_eq!(second, Some(vec![3]));
+        let (end, paginator) = paginator.next_page().wait().unwrap();
+        assert_eq!(end, None);
+        assert_eq!(paginator.next_page().wait().unwrap().0, None);
+    }
+
+    #[test]
+    fn mixes_items_and_pages() {
+        let paginator = Paginator::new(fake_pages(vec![vec![1, 2, 3], vec![4]]));
+        let (first, paginator) = paginator.into_future().wait().map_err(|(e, _)| e).unwrap();
+        assert_eq!(first, Some(1));
+        let (rest, paginator) = paginator.next_page().wait().unwrap();
+        assert_eq!(rest, Some(vec![2, 3]));
+        assert_eq!(paginator.collect().wait().unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn stops_after_errors() {
+        let fetch = |page| -> future::FutureResult<Response<Vec<u32>>, Error> {
+            if page == 1 {
+                future::err("page 1 is broken".into())
+            } else {
+                fake_pages(vec![vec![1], vec![2], vec![3]])(page)
+            }
+        };
+        let mut paginator = Paginator::new(fetch).wait();
+        assert_eq!(paginator.next().unwrap().unwrap(), 1);
+        assert!(paginator.next().unwrap().is_err());
+        assert!(paginator.next().is_none());
+    }
+
+    #[test]
+    fn stops_at_the_limits() {
+        let pages = vec![vec![1, 2], vec![3, 4], vec![5]];
+        let paginator = Paginator::new(fake_pages(pages.clone())).max_pages(2);
+        assert_eq!(paginator.collect().wait().unwrap(), vec![1, 2, 3, 4]);
+
+        let paginator = Paginator::new(fake_pages(pages.clone())).max_items(3);
+        let (first, paginator) = paginator.next_page().wait().unwrap();
+        assert_eq!(first, Some(vec![1, 2]));
+        assert_eq!(paginator.collect().wait().unwrap(), vec![3]);
+
+        let mut fetched = 0;
+        let fetch = |page| {
+            fetched += 1;
+            fake_pages(vec![vec![1, 2], vec![3]])(page)
+        };
+        let items = Paginator::new(fetch).max_items(2).collect().wait().unwrap();
+        assert_eq!((items, fetched), (vec![1, 2], 1));
+    }
+
+    #[test]
+    fn fetches_lazily() {
+        let mut fetched = Vec::new();
+        {
+            let fetch = |page| {
+                fetched.push(page);
+                fake_pages(vec![vec![1, 2], vec![3]])(page)
+            };
+            let first = Paginator::new(fetch).take(2).collect().wait().unwrap();
+            assert_eq!(first, vec![1, 2]);
+        }
+        assert_eq!(fetched, vec![0]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Retrying requests that failed because of rate limiting or server errors.
+
+use std::{cmp, u64};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::StatusCode;
+
+const DEFAULT_BASE_DELAY_MS: u64 = 500;
+const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+
+/// Controls whether and how requests are retried when imgur responds with
+/// `429 Too Many Requests` or a server error.
+///
+/// The delay before retry number `n` (counting from 0) is picked uniformly at random
+/// from `[0, min(max_delay, base_delay * 2^n)]` (the "full jitter" algorithm), so that
+/// many clients hitting the same rate limit don't all come back at the same moment.
+///
+/// Clones of a policy share the same random number generator.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    rng: Arc<Mutex<XorShift>>,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries. This is the default.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(0)
+    }
+
+    /// A policy that retries up to `max_retries` times, with a base delay of 500ms and a
+    /// maximum delay of 30s.
+    pub fn new(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+            rng: Arc::new(Mutex::new(XorShift::from_time())),
+        }
+    }
+
+    /// Sets the delay the exponential backoff starts from.
+    pub fn base_delay(mut self, delay: Duration) -> RetryPolicy {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Sets the upper bound for a single delay.
+    pub fn max_delay(mut self, delay: Duration) -> RetryPolicy {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Seeds the random number generator used for the jitter, which makes the sequence
+    /// of delays deterministic.
+    pub fn seed(mut self, seed: u64) -> RetryPolicy {
+        self.rng = Arc::new(Mutex::new(XorShift::new(seed)));
+        self
+    }
+
+    /// The maximum number of retries for a single request.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Picks the delay before retry number `attempt` (counting from 0).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = duration_to_nanos(self.base_delay);
+        let max = duration_to_nanos(self.max_delay);
+        let exponential = base.saturating_mul(1u64 << cmp::min(attempt, 63));
+        let cap = cmp::min(max, exponential);
+        let random = self.rng.lock().unwrap().next();
+        let nanos = if cap == u64::MAX {
+            random
+        } else {
+            random % (cap + 1)
+        };
+        Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::none()
+    }
+}
+
+/// Whether a response with this status is worth retrying.
+pub(crate) fn should_retry(status: StatusCode) -> bool {
+    status == StatusCode::TooManyRequests || status.is_server_error()
+}
+
+fn duration_to_nanos(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .saturating_mul(1_000_000_000)
+        .saturating_add(u64::from(duration.subsec_nanos()))
+}
+
+/// A xorshift64* generator. Plenty for spreading out retries, and it keeps the crate
+/// free of a dependency on `rand`.
+#[derive(Debug)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // The state must never be zero.
+        XorShift(if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        })
+    }
+
+    fn from_time() -> XorShift {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0));
+        XorShift::new(duration_to_nanos(now))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_delays_are_deterministic() {
+        let a = RetryPolicy::new(5).seed(42);
+        let b = RetryPolicy::new(5).seed(42);
+        for attempt in 0..5 {
+            assert_eq!(a.delay(attempt), b.delay(attempt));
+        }
+    }
+
+    #[test]
+    fn delays_stay_within_exponential_bounds() {
+        let policy = RetryPolicy::new(10)
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_secs(1))
+            .seed(7);
+        for attempt in 0..10 {
+            let cap = cmp::min(100 * (1 << attempt), 1000);
+            for _ in 0..100 {
+                assert!(policy.delay(attempt) <= Duration::from_millis(cap));
+            }
+        }
+    }
+
+    #[test]
+    fn delays_are_jittered() {
+        let policy = RetryPolicy::new(1).seed(1);
+        let first = policy.delay(3);
+        assert!((0..20).any(|_| policy.delay(3) != first));
+    }
+
+    #[test]
+    fn retries_rate_limits_and_server_errors() {
+        assert!(should_retry(StatusCode::TooManyRequests));
+        assert!(should_retry(StatusCode::ServiceUnavailable));
+        assert!(!should_retry(StatusCode::NotFound));
+        assert!(!should_retry(StatusCode::Ok));
+    }
+}
@@ -0,0 +1,243 @@
+//! Incrementally parsing responses whose `data` is a large array.
+
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use {Error, Response, Result};
+
+/// Parses a response of the form `{"data": [...], ...}` as its bytes arrive, yielding
+/// the array's elements as soon as each is complete. Only the current element is kept
+/// in memory, not the whole body.
+///
+/// If `data` turns out not to be an array (an error response), the body is buffered
+/// and parsed as a whole by `finish`.
+#[derive(Debug)]
+pub(crate) struct DataArrayParser<T> {
+    state: State,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Everything before the array (or the whole body, if there is no array).
+    head: Vec<u8>,
+    /// Where the string currently being read started in `head`, at the top level.
+    key_start: usize,
+    /// The last top-level string, which is the key when followed by a `:`.
+    last_key: Vec<u8>,
+    /// The array element read so far.
+    element: Vec<u8>,
+    marker: PhantomData<T>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Looking for the `data` key.
+    Head,
+    /// Between `"data":` and its value.
+    DataValue,
+    /// Inside the `data` array.
+    Array,
+    /// After the `data` array, where nothing interesting is left.
+    Tail,
+    /// `data` isn't an array.
+    NotArray,
+}
+
+impl<T: DeserializeOwned> DataArrayParser<T> {
+    pub(crate) fn new() -> DataArrayParser<T> {
+        DataArrayParser {
+            state: State::Head,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            head: Vec::new(),
+            key_start: 0,
+            last_key: Vec::new(),
+            element: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Feeds the next chunk of the body, returning the elements it completed.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        for &byte in chunk {
+            match self.state {
+                State::Head => self.head_byte(byte),
+                State::DataValue => {
+                    if byte == b'[' {
+                        self.state = State::Array;
+                        self.depth = 2;
+                    } else {
+                        self.head.push(byte);
+                        if !is_whitespace(byte) {
+                            self.state = State::NotArray;
+                        }
+                    }
+                }
+                State::Array => {
+                    if let Some(item) = self.array_byte(byte)? {
+                        items.push(item);
+                    }
+                }
+                State::Tail => {}
+                State::NotArray => self.head.push(byte),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Ends the body, returning the data of an error response as an error.
+    pub(crate) fn finish(&mut self) -> Result<Vec<T>> {
+        match self.state {
+            State::Tail => Ok(Vec::new()),
+            State::Array => Err("response ended in the middle of the data array".into()),
+            State::Head | State::DataValue | State::NotArray => {
+                let response: Response<Vec<T>> = serde_json::from_slice(&self.head)?;
+                response.data.into_result()
+            }
+        }
+    }
+
+    fn head_byte(&mut self, byte: u8) {
+        self.head.push(byte);
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if self.depth == 1 {
+                    let end = self.head.len() - 1;
+                    self.last_key = self.head[self.key_start..end].to_vec();
+                }
+            }
+            return;
+        }
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                self.key_start = self.head.len();
+            }
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+            b':' if self.depth == 1 && self.last_key == b"data" => self.state = State::DataValue,
+            b',' if self.depth == 1 => self.last_key.clear(),
+            _ => {}
+        }
+    }
+
+    fn array_byte(&mut self, byte: u8) -> Result<Option<T>> {
+        if self.in_string {
+            self.element.push(byte);
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+            }
+            return Ok(None);
+        }
+        if self.depth == 2 && (byte == b',' || byte == b']') {
+            if byte == b']' {
+                self.state = State::Tail;
+            }
+            if self.element.iter().all(|&b| is_whitespace(b)) {
+                self.element.clear();
+                return Ok(None);
+            }
+            let item = serde_json::from_slice(&self.element).map_err(Error::from);
+            self.element.clear();
+            return item.map(Some);
+        }
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' if self.depth == 2 => {
+                return Err("unbalanced brackets in the data array".into())
+            }
+            b'}' | b']' => self.depth -= 1,
+            _ => {}
+        }
+        self.element.push(byte);
+        Ok(None)
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    byte == b' ' || byte == b'\n' || byte == b'\r' || byte == b'\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ErrorKind;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        id: String,
+        tags: Vec<String>,
+    }
+
+    const BODY: &[u8] = br#"{"data": [
+        {"id": "a", "tags": ["x,y", "]"]},
+        {"id": "b\"}", "tags": []}
+    ], "success": true, "status": 200}"#;
+
+    fn parse_in_chunks(body: &[u8], chunk_size: usize) -> Result<Vec<Item>> {
+        let mut parser = DataArrayParser::new();
+        let mut items = Vec::new();
+        for chunk in body.chunks(chunk_size) {
+            items.extend(parser.feed(chunk)?);
+        }
+        items.extend(parser.finish()?);
+        Ok(items)
+    }
+
+    #[test]
+    fn yields_elements_regardless_of_chunking() {
+        for &chunk_size in &[1, 7, BODY.len()] {
+            let items = parse_in_chunks(BODY, chunk_size).unwrap();
+            let ids: Vec<&str> = items.iter().map(|item| item.id.as_str()).collect();
+            assert_eq!(ids, ["a", "b\"}"]);
+            assert_eq!(items[0].tags, ["x,y", "]"]);
+        }
+    }
+
+    #[test]
+    fn yields_elements_as_they_complete() {
+        let mut parser: DataArrayParser<Item> = DataArrayParser::new();
+        let split = BODY.iter().position(|&b| b == b'b').unwrap();
+        assert_eq!(parser.feed(&BODY[..split]).unwrap().len(), 1);
+        assert_eq!(parser.feed(&BODY[split..]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn handles_empty_arrays_and_key_order() {
+        let body = br#"{"success": true, "status": 200, "data": []}"#;
+        assert!(parse_in_chunks(body, 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_error_responses() {
+        let body = br#"{"data": {"error": "Not found", "request": "/3/album/x/images",
+            "method": "GET"}, "success": false, "status": 404}"#;
+        match *parse_in_chunks(body, 5).unwrap_err().kind() {
+            ErrorKind::Imgur(ref e) => assert_eq!(e.error, "Not found"),
+            ref other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_unbalanced_brackets() {
+        assert!(parse_in_chunks(br#"{"data": [{"id": "a"}}]}"#, 4).is_err());
+    }
+
+    #[test]
+    fn reports_truncated_bodies() {
+        assert!(parse_in_chunks(&BODY[..40], 8).is_err());
+    }
+}
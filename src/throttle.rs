@@ -0,0 +1,129 @@
+//! Spreading requests out when few credits are left, instead of running into `429`s.
+
+use std::{cmp, u32};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use RateLimitInfo;
+
+const DEFAULT_FALLBACK_WINDOW_SECS: u64 = 60 * 60;
+
+/// Slows the client down once the credits left drop to a threshold, spacing out the
+/// remaining requests evenly until the credits are reset rather than using them up in
+/// a burst. Configure it with `ImgurClientBuilder::throttle`.
+///
+/// The reset time is taken from `RateLimitInfo::user_reset`. imgur doesn't say when
+/// the application's credits are reset, so without a user reset time in the future the
+/// requests are spread over the fallback window instead, an hour by default.
+///
+/// Clones of a throttle (and so clones of a client) share their schedule, so requests
+/// from all of them are spaced out together.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    threshold: u64,
+    fallback_window: Duration,
+    next_slot: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Throttle {
+    /// A throttle that kicks in once `RateLimitInfo::remaining` is at or below
+    /// `threshold`.
+    pub fn new(threshold: u64) -> Throttle {
+        Throttle {
+            threshold,
+            fallback_window: Duration::from_secs(DEFAULT_FALLBACK_WINDOW_SECS),
+            next_slot: Arc::default(),
+        }
+    }
+
+    /// Sets the time to spread requests over when the reset time isn't known.
+    pub fn fallback_window(mut self, window: Duration) -> Throttle {
+        self.fallback_window = window;
+        self
+    }
+
+    /// Reserves a slot for a request, returning how long to wait before sending it, if
+    /// at all.
+    pub(crate) fn reserve(&self, info: Option<RateLimitInfo>) -> Option<Duration> {
+        let now_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        self.reserve_at(info, now_unix, Instant::now())
+    }
+
+    fn reserve_at(
+        &self,
+        info: Option<RateLimitInfo>,
+        now_unix: u64,
+        now: Instant,
+    ) -> Option<Duration> {
+        let info = info?;
+        let remaining = info.remaining()?;
+        if remaining > self.threshold {
+            return None;
+        }
+        let window = match info.user_reset {
+            Some(reset) if reset > now_unix => Duration::from_secs(reset - now_unix),
+            _ => self.fallback_window,
+        };
+        let spacing = window / cmp::min(remaining + 1, u64::from(u32::MAX)) as u32;
+
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = match *next_slot {
+            Some(slot) if slot > now => slot,
+            _ => now,
+        };
+        *next_slot = Some(slot + spacing);
+        if slot > now {
+            Some(slot - now)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(remaining: u64, reset: Option<u64>) -> Option<RateLimitInfo> {
+        Some(RateLimitInfo {
+            user_remaining: Some(remaining),
+            user_reset: reset,
+            ..RateLimitInfo::default()
+        })
+    }
+
+    #[test]
+    fn leaves_requests_alone_above_the_threshold() {
+        let throttle = Throttle::new(10);
+        let now = Instant::now();
+        assert_eq!(throttle.reserve_at(None, 1000, now), None);
+        for _ in 0..3 {
+            assert_eq!(throttle.reserve_at(info(11, Some(1100)), 1000, now), None);
+        }
+    }
+
+    #[test]
+    fn spaces_requests_until_the_reset() {
+        let throttle = Throttle::new(10);
+        let now = Instant::now();
+        // 4 credits left, reset in 100s: one request every 20s.
+        assert_eq!(throttle.reserve_at(info(4, Some(1100)), 1000, now), None);
+        let second = throttle.reserve_at(info(4, Some(1100)), 1000, now);
+        assert_eq!(second, Some(Duration::from_secs(20)));
+        let third = throttle.reserve_at(info(4, Some(1100)), 1000, now);
+        assert_eq!(third, Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn falls_back_without_a_reset_time() {
+        let throttle = Throttle::new(10).fallback_window(Duration::from_secs(60));
+        let now = Instant::now();
+        let past_reset = info(2, Some(900));
+        assert_eq!(throttle.reserve_at(past_reset, 1000, now), None);
+        let next = throttle.reserve_at(past_reset, 1000, now);
+        assert_eq!(next, Some(Duration::from_secs(20)));
+    }
+}
@@ -0,0 +1,34 @@
+//! The delays for retries and throttling, independent of the event loop.
+
+use std::fmt;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-core")]
+use futures::future;
+use futures::Future;
+#[cfg(feature = "tokio-core")]
+use tokio_core::reactor::{Handle, Timeout};
+
+use Error;
+
+/// Creates the delays the client waits for before retrying a request (see
+/// `RetryPolicy`) and while throttling (see `Throttle`).
+///
+/// This is the only thing the client needs an event loop for besides the `hyper`
+/// client itself, so `ImgurClientBuilder::build_with_timer` can create a client that
+/// doesn't depend on `tokio_core`. With the `tokio-core` feature, which is on by
+/// default, it's implemented for `tokio_core`'s `Handle`.
+pub trait Timer: fmt::Debug {
+    /// A future that resolves once `delay` has passed.
+    fn delay(&self, delay: Duration) -> Box<dyn Future<Item = (), Error = Error>>;
+}
+
+#[cfg(feature = "tokio-core")]
+impl Timer for Handle {
+    fn delay(&self, delay: Duration) -> Box<dyn Future<Item = (), Error = Error>> {
+        let timeout = future::result(Timeout::new(delay, self))
+            .flatten()
+            .map_err(Error::from);
+        Box::new(timeout)
+    }
+}